@@ -0,0 +1,401 @@
+//! Admin API for operating the mint
+//!
+//! Served on its own address so it can be kept off the public interface. Every
+//! request has to carry `Authorization: Bearer <admin.api_key>`.
+
+use std::sync::Arc;
+
+use axum::extract::{Json, Path, Query, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::{Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use cdk::cdk_database::{self, MintDatabase};
+use cdk::mint::Mint;
+use cdk::nuts::{CurrencyUnit, KeysetResponse, MintQuoteBolt11Response};
+use cdk::types::{MeltQuote, MintQuote};
+use cdk::Amount;
+use ln_rs::InvoiceStatus;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::config::MAX_ORDER_RANGE;
+use crate::db_queries::{self, Page, Quote};
+use crate::error::{into_response, Error};
+use crate::keysets;
+use crate::lightning::Lightning;
+use crate::maintenance::Maintenance;
+use crate::types::{AuditResponse, UnitAudit};
+use crate::webhook::Webhook;
+
+#[derive(Clone)]
+pub struct AdminState {
+    pub mint: Arc<RwLock<Mint>>,
+    pub ln: Arc<dyn Lightning>,
+    pub localstore: Arc<dyn MintDatabase<Err = cdk_database::Error> + Send + Sync>,
+    pub api_key: String,
+    pub max_order: u8,
+    pub maintenance: Maintenance,
+    pub webhook: Option<Webhook>,
+}
+
+pub fn router(state: AdminState) -> Router {
+    Router::new()
+        .route("/rotate-keyset", post(post_rotate_keyset))
+        .route("/audit", get(get_audit))
+        .route("/quote/:quote_id/mark-paid", post(post_mark_quote_paid))
+        .route("/mint-quotes", get(get_mint_quotes))
+        .route("/melt-quotes", get(get_melt_quotes))
+        .route("/quote/:quote_id", get(get_quote))
+        .route("/pause", post(post_pause))
+        .route("/resume", post(post_resume))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth))
+        .with_state(state)
+}
+
+async fn auth<B>(State(state): State<AdminState>, request: Request<B>, next: Next<B>) -> Response {
+    let authorized = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|key| key.eq(&state.api_key));
+
+    if !authorized {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    next.run(request).await
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RotateKeysetRequest {
+    pub unit: CurrencyUnit,
+    pub max_order: Option<u8>,
+}
+
+/// Replace the active keyset of a unit with a new one
+///
+/// The previous keyset becomes inactive but is still accepted in swaps and
+/// melts.
+async fn post_rotate_keyset(
+    State(state): State<AdminState>,
+    Json(payload): Json<RotateKeysetRequest>,
+) -> Result<Json<KeysetResponse>, Response> {
+    let max_order = payload.max_order.unwrap_or(state.max_order);
+    if !MAX_ORDER_RANGE.contains(&max_order) {
+        return Err(StatusCode::BAD_REQUEST.into_response());
+    }
+
+    let mint = state.mint.write().await;
+
+    keysets::rotate_keyset(&mint, &state.localstore, &payload.unit, max_order)
+        .await
+        .map_err(|err| err.into_response())?;
+
+    Ok(Json(mint.keysets().await.map_err(into_response)?))
+}
+
+/// Ecash issued through paid mint quotes and redeemed through paid melt quotes,
+/// next to the balance backing it
+///
+/// Redeemed is the quoted amount of melts, see [`db_queries::unit_totals`].
+async fn get_audit(State(state): State<AdminState>) -> Result<Json<AuditResponse>, Response> {
+    let totals = db_queries::unit_totals(&state.localstore)
+        .await
+        .map_err(|err| into_response(err.into()))?;
+
+    let outstanding_msat = db_queries::outstanding_msat(&totals);
+
+    let units = totals
+        .into_iter()
+        .map(|(unit, (issued, redeemed))| UnitAudit {
+            unit,
+            issued: Amount::from(issued),
+            redeemed: Amount::from(redeemed),
+            outstanding: Amount::from(issued.saturating_sub(redeemed)),
+        })
+        .collect();
+
+    let balance = state
+        .ln
+        .balance()
+        .await
+        .map_err(|err| Error::Ln(err).into_response())?;
+
+    Ok(Json(AuditResponse {
+        units,
+        ln_balance: balance.ln_msat / 1000,
+        on_chain_balance: balance.on_chain_sat,
+        backing_ratio: (outstanding_msat > 0)
+            .then(|| balance.ln_msat as f64 / outstanding_msat as f64),
+    }))
+}
+
+/// Mark a mint quote paid after a payment the invoice watcher missed
+///
+/// Only succeeds if the ln backend reports the invoice of the quote as paid.
+async fn post_mark_quote_paid(
+    State(state): State<AdminState>,
+    Path(quote_id): Path<String>,
+) -> Result<Json<MintQuoteBolt11Response>, Response> {
+    let quote = state
+        .localstore
+        .get_mint_quote(&quote_id)
+        .await
+        .map_err(|err| into_response(err.into()))?
+        .ok_or(StatusCode::NOT_FOUND.into_response())?;
+
+    let status = crate::settle_mint_quote(
+        &state.mint,
+        state.ln.as_ref(),
+        state.webhook.as_ref(),
+        quote,
+    )
+    .await
+    .map_err(|err| err.into_response())?;
+
+    if status != InvoiceStatus::Paid {
+        warn!(
+            target: "audit",
+            "Refused to mark quote {} paid, backend reports {:?}", quote_id, status
+        );
+        return Err(Error::QuoteNotPaid.into_response());
+    }
+
+    info!(target: "audit", "Marked mint quote {} paid", quote_id);
+
+    Ok(Json(
+        state
+            .mint
+            .read()
+            .await
+            .check_mint_quote(&quote_id)
+            .await
+            .map_err(into_response)?,
+    ))
+}
+
+/// Refuse new quotes, mints, melts and swaps until resumed
+async fn post_pause(State(state): State<AdminState>) -> Result<StatusCode, Response> {
+    state
+        .maintenance
+        .set_paused(true)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+
+    info!(target: "audit", "Mint paused for maintenance");
+
+    Ok(StatusCode::OK)
+}
+
+async fn post_resume(State(state): State<AdminState>) -> Result<StatusCode, Response> {
+    state
+        .maintenance
+        .set_paused(false)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())?;
+
+    info!(target: "audit", "Mint resumed");
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageQuery {
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default = "page_limit_default")]
+    pub limit: usize,
+}
+
+fn page_limit_default() -> usize {
+    50
+}
+
+/// Mint quotes, most recent first
+async fn get_mint_quotes(
+    State(state): State<AdminState>,
+    Query(query): Query<PageQuery>,
+) -> Result<Json<Page<MintQuote>>, Response> {
+    let page = db_queries::recent_mint_quotes(&state.localstore, query.offset, query.limit)
+        .await
+        .map_err(|err| into_response(err.into()))?;
+
+    Ok(Json(page))
+}
+
+/// Melt quotes, most recent first
+async fn get_melt_quotes(
+    State(state): State<AdminState>,
+    Query(query): Query<PageQuery>,
+) -> Result<Json<Page<MeltQuote>>, Response> {
+    let page = db_queries::recent_melt_quotes(&state.localstore, query.offset, query.limit)
+        .await
+        .map_err(|err| into_response(err.into()))?;
+
+    Ok(Json(page))
+}
+
+async fn get_quote(
+    State(state): State<AdminState>,
+    Path(quote_id): Path<String>,
+) -> Result<Json<Quote>, Response> {
+    let quote = db_queries::quote_by_id(&state.localstore, &quote_id)
+        .await
+        .map_err(|err| into_response(err.into()))?
+        .ok_or(StatusCode::NOT_FOUND.into_response())?;
+
+    Ok(Json(quote))
+}
+
+#[cfg(test)]
+mod tests {
+    use cdk::nuts::Id;
+
+    use crate::utils::unix_time;
+
+    use super::*;
+    use crate::test_utils::{mint_on, test_invoice, test_localstore, MockLightning};
+
+    async fn test_state() -> AdminState {
+        let localstore = test_localstore();
+        let paused = std::env::temp_dir().join(format!(
+            "cashu-rs-mint-test-{}.paused",
+            rand::random::<u64>()
+        ));
+
+        AdminState {
+            mint: Arc::new(RwLock::new(mint_on(localstore.clone()).await)),
+            ln: Arc::new(MockLightning::default()),
+            localstore,
+            api_key: "key".to_string(),
+            max_order: 32,
+            maintenance: Maintenance::load(paused),
+            webhook: None,
+        }
+    }
+
+    async fn rotate(state: &AdminState, unit: CurrencyUnit) -> KeysetResponse {
+        let Json(keysets) = post_rotate_keyset(
+            State(state.clone()),
+            Json(RotateKeysetRequest {
+                unit,
+                max_order: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        keysets
+    }
+
+    async fn active_sat_keyset(state: &AdminState) -> Id {
+        state
+            .localstore
+            .get_active_keyset_id(&CurrencyUnit::Sat)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn rotation_replaces_the_active_keyset() {
+        let state = test_state().await;
+        let old_id = active_sat_keyset(&state).await;
+
+        let keysets = rotate(&state, CurrencyUnit::Sat).await;
+
+        let new_id = active_sat_keyset(&state).await;
+        assert_ne!(new_id, old_id);
+
+        let active: Vec<(Id, bool)> = keysets
+            .keysets
+            .iter()
+            .map(|keyset| (keyset.id, keyset.active))
+            .collect();
+        assert!(active.contains(&(new_id, true)));
+        assert!(active.contains(&(old_id, false)));
+
+        let old = state
+            .localstore
+            .get_keyset_info(&old_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!old.active);
+        assert!(old.valid_to.is_some());
+
+        // The next rotation derives yet another keyset
+        rotate(&state, CurrencyUnit::Sat).await;
+        let newest_id = active_sat_keyset(&state).await;
+        assert_ne!(newest_id, new_id);
+        assert_ne!(newest_id, old_id);
+    }
+
+    #[tokio::test]
+    async fn audit_counts_the_quoted_melt_amount() {
+        let state = test_state().await;
+        let mint = state.mint.read().await;
+
+        let mint_quote = mint
+            .new_mint_quote(
+                "https://example.com/".to_string().into(),
+                test_invoice(64_000, String::new()).to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(64),
+                unix_time() + 60,
+            )
+            .await
+            .unwrap();
+        mint.update_mint_quote(MintQuote {
+            paid: true,
+            ..mint_quote
+        })
+        .await
+        .unwrap();
+
+        // Paid with 20 sat of fee reserve, whatever the routing fee was
+        let melt_quote = mint
+            .new_melt_quote(
+                test_invoice(40_000, String::new()).to_string(),
+                CurrencyUnit::Sat,
+                Amount::from(40),
+                Amount::from(20),
+                unix_time() + 60,
+            )
+            .await
+            .unwrap();
+        state
+            .localstore
+            .add_melt_quote(MeltQuote {
+                paid: true,
+                ..melt_quote
+            })
+            .await
+            .unwrap();
+
+        // An unpaid melt is not redeemed
+        mint.new_melt_quote(
+            test_invoice(10_000, String::new()).to_string(),
+            CurrencyUnit::Sat,
+            Amount::from(10),
+            Amount::from(1),
+            unix_time() + 60,
+        )
+        .await
+        .unwrap();
+        drop(mint);
+
+        let Json(audit) = get_audit(State(state)).await.unwrap();
+
+        assert_eq!(audit.units.len(), 1);
+        let sat = &audit.units[0];
+        assert_eq!(sat.unit, CurrencyUnit::Sat);
+        assert_eq!(sat.issued, Amount::from(64));
+        assert_eq!(sat.redeemed, Amount::from(40));
+        assert_eq!(sat.outstanding, Amount::from(24));
+        assert_eq!(audit.backing_ratio, Some(0.0));
+    }
+}
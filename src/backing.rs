@@ -0,0 +1,120 @@
+//! Cache of the inputs of the backing ratio check
+//!
+//! Summing outstanding ecash scans every quote and the ln balance is an RPC to
+//! the node, so both are reused for [`BACKING_CACHE_TTL`]. Mint quotes accepted
+//! in between are added to the cached outstanding amount, a burst of quotes
+//! can't get past the ratio while the cache is fresh.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// How long summed outstanding ecash and the ln balance are reused
+const BACKING_CACHE_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy)]
+struct Snapshot {
+    outstanding_msat: u64,
+    balance_msat: u64,
+    taken: Instant,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BackingCache {
+    snapshot: Arc<Mutex<Option<Snapshot>>>,
+}
+
+impl BackingCache {
+    /// Check the ln balance covers outstanding ecash at `min_ratio` after
+    /// adding `amount_msat`, and count it as outstanding if it does
+    ///
+    /// `load` returns the outstanding and ln balance msat, it is only called
+    /// when there is no snapshot younger than [`BACKING_CACHE_TTL`].
+    pub async fn check<F, Fut, E>(
+        &self,
+        amount_msat: u64,
+        min_ratio: f64,
+        load: F,
+    ) -> Result<bool, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<(u64, u64), E>>,
+    {
+        // Held while loading so concurrent quotes wait for one load
+        let mut snapshot = self.snapshot.lock().await;
+
+        let mut current = match *snapshot {
+            Some(current) if current.taken.elapsed() < BACKING_CACHE_TTL => current,
+            _ => {
+                let (outstanding_msat, balance_msat) = load().await?;
+                Snapshot {
+                    outstanding_msat,
+                    balance_msat,
+                    taken: Instant::now(),
+                }
+            }
+        };
+
+        let outstanding_msat = current.outstanding_msat.saturating_add(amount_msat);
+
+        let backed = is_backed(current.balance_msat, outstanding_msat, min_ratio);
+        match backed {
+            true => current.outstanding_msat = outstanding_msat,
+            false => warn!(
+                "Refusing mint quote, {} msat outstanding against {} msat ln balance",
+                outstanding_msat, current.balance_msat
+            ),
+        }
+
+        *snapshot = Some(current);
+
+        Ok(backed)
+    }
+}
+
+/// Whether `balance_msat` covers `outstanding_msat` at `min_ratio`
+fn is_backed(balance_msat: u64, outstanding_msat: u64, min_ratio: f64) -> bool {
+    balance_msat as f64 >= outstanding_msat as f64 * min_ratio
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn ratio_is_inclusive() {
+        assert!(is_backed(1000, 1000, 1.0));
+        assert!(!is_backed(999, 1000, 1.0));
+        assert!(is_backed(500, 1000, 0.5));
+        assert!(!is_backed(499, 1000, 0.5));
+        assert!(is_backed(0, 0, 1.0));
+    }
+
+    #[tokio::test]
+    async fn accepted_quotes_count_until_reload() {
+        let cache = BackingCache::default();
+        let loads = AtomicUsize::new(0);
+
+        let check = |amount_msat| {
+            cache.check(amount_msat, 1.0, || async {
+                loads.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, Infallible>((0, 1000))
+            })
+        };
+
+        assert!(check(600).await.unwrap());
+        assert!(check(400).await.unwrap());
+        assert!(!check(1).await.unwrap());
+
+        // A refused quote is not counted
+        assert!(check(0).await.unwrap());
+
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+    }
+}
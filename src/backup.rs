@@ -0,0 +1,355 @@
+//! Backup and restore of the mint database
+//!
+//! A backup is written as JSON lines, one record per line, so it never has to
+//! hold the whole database in memory. Keysets and quotes go through the cdk
+//! `MintDatabase`. It has no way to enumerate spent proofs, so those are read
+//! from and written to the cdk-redb table directly and backups need the redb
+//! engine. Restoring keysets without the spent proofs would make every spent
+//! token spendable again.
+//!
+//! The issued, redeemed and outstanding amounts per unit are part of the
+//! backup, an import checks them against the restored quotes.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail};
+use cdk::cdk_database::{self, MintDatabase};
+use cdk::mint::MintKeySetInfo;
+use cdk::nuts::{CurrencyUnit, Id, Proof};
+use cdk::types::{MeltQuote, MintQuote};
+use cdk::Amount;
+use cdk_redb::MintRedbDatabase;
+use redb::{Database, ReadableTable, TableDefinition, TableError};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::config::DatabaseEngine;
+use crate::db_queries;
+use crate::types::UnitAudit;
+
+type Localstore = Arc<dyn MintDatabase<Err = cdk_database::Error> + Send + Sync>;
+
+/// Json encoded spent [`Proof`] by `Y`, as cdk-redb stores them
+const SPENT_PROOFS_TABLE: TableDefinition<[u8; 33], &str> = TableDefinition::new("spent_proofs");
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+enum Record {
+    KeysetInfo(MintKeySetInfo),
+    ActiveKeyset { unit: CurrencyUnit, id: Id },
+    MintQuote(MintQuote),
+    MeltQuote(MeltQuote),
+    SpentProof(Proof),
+    UnitTotals(UnitAudit),
+}
+
+fn write_record<W: Write>(writer: &mut W, record: &Record) -> anyhow::Result<()> {
+    serde_json::to_writer(&mut *writer, record)?;
+    writer.write_all(b"\n")?;
+
+    Ok(())
+}
+
+/// Records of the backup at `input`, read as they are iterated
+fn read_records(input: &Path) -> anyhow::Result<impl Iterator<Item = anyhow::Result<Record>>> {
+    let reader = BufReader::new(File::open(input)?);
+
+    Ok(reader
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.is_empty()))
+        .map(|line| -> anyhow::Result<Record> { Ok(serde_json::from_str(&line?)?) }))
+}
+
+fn check_engine(engine: &DatabaseEngine) -> anyhow::Result<()> {
+    match engine {
+        DatabaseEngine::Redb => Ok(()),
+        DatabaseEngine::Sqlite => {
+            bail!("Backups need the redb engine, spent proofs can't be read from sqlite")
+        }
+    }
+}
+
+fn open_localstore(db_path: &Path) -> anyhow::Result<Localstore> {
+    let path = db_path.to_str().ok_or(anyhow!(
+        "Db path {} is not valid unicode",
+        db_path.display()
+    ))?;
+
+    Ok(Arc::new(MintRedbDatabase::new(path)?))
+}
+
+/// Write keysets, quotes, spent proofs and totals of the database at
+/// `db_path` to `out`
+pub async fn export(engine: &DatabaseEngine, db_path: &Path, out: &Path) -> anyhow::Result<()> {
+    check_engine(engine)?;
+
+    let mut writer = BufWriter::new(File::create(out)?);
+
+    // cdk-redb holds the database open, so the spent proofs are read first
+    let mut count = export_spent_proofs(db_path, &mut writer)?;
+
+    let localstore = open_localstore(db_path)?;
+
+    for keyset_info in localstore.get_keyset_infos().await? {
+        write_record(&mut writer, &Record::KeysetInfo(keyset_info))?;
+        count += 1;
+    }
+
+    for (unit, id) in localstore.get_active_keysets().await? {
+        write_record(&mut writer, &Record::ActiveKeyset { unit, id })?;
+        count += 1;
+    }
+
+    for quote in localstore.get_mint_quotes().await? {
+        write_record(&mut writer, &Record::MintQuote(quote))?;
+        count += 1;
+    }
+
+    for quote in localstore.get_melt_quotes().await? {
+        write_record(&mut writer, &Record::MeltQuote(quote))?;
+        count += 1;
+    }
+
+    for (unit, (issued, redeemed)) in db_queries::unit_totals(&localstore).await? {
+        let totals = UnitAudit {
+            unit,
+            issued: Amount::from(issued),
+            redeemed: Amount::from(redeemed),
+            outstanding: Amount::from(issued.saturating_sub(redeemed)),
+        };
+        write_record(&mut writer, &Record::UnitTotals(totals))?;
+        count += 1;
+    }
+
+    writer.flush()?;
+
+    info!("Exported {} records to {}", count, out.display());
+
+    Ok(())
+}
+
+/// Restore the backup at `input` into the empty database at `db_path`
+pub async fn import(engine: &DatabaseEngine, db_path: &Path, input: &Path) -> anyhow::Result<()> {
+    check_engine(engine)?;
+
+    let localstore = open_localstore(db_path)?;
+
+    if !localstore.get_keyset_infos().await?.is_empty() {
+        bail!("Database already contains keysets, import requires a fresh database");
+    }
+
+    let mut count = 0;
+    let mut totals = HashMap::new();
+
+    for record in read_records(input)? {
+        match record? {
+            Record::KeysetInfo(keyset_info) => localstore.add_keyset_info(keyset_info).await?,
+            Record::ActiveKeyset { unit, id } => localstore.add_active_keyset(unit, id).await?,
+            Record::MintQuote(quote) => localstore.add_mint_quote(quote).await?,
+            Record::MeltQuote(quote) => localstore.add_melt_quote(quote).await?,
+            // Written below once cdk-redb has closed the database
+            Record::SpentProof(_) => continue,
+            Record::UnitTotals(unit_totals) => {
+                totals.insert(
+                    unit_totals.unit,
+                    (
+                        u64::from(unit_totals.issued),
+                        u64::from(unit_totals.redeemed),
+                    ),
+                );
+                continue;
+            }
+        }
+        count += 1;
+    }
+
+    if db_queries::unit_totals(&localstore).await? != totals {
+        bail!("Quotes of the backup do not add up to its totals, it is incomplete");
+    }
+
+    drop(localstore);
+
+    count += import_spent_proofs(db_path, input)?;
+
+    info!("Imported {} records from {}", count, input.display());
+
+    Ok(())
+}
+
+/// Write the spent proofs of the database at `db_path` as records
+fn export_spent_proofs<W: Write>(db_path: &Path, writer: &mut W) -> anyhow::Result<usize> {
+    let db = Database::open(db_path)?;
+    let read_txn = db.begin_read()?;
+
+    let table = match read_txn.open_table(SPENT_PROOFS_TABLE) {
+        Ok(table) => table,
+        // Nothing was spent yet
+        Err(TableError::TableDoesNotExist(_)) => return Ok(0),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut count = 0;
+    for entry in table.iter()? {
+        let (_, proof) = entry?;
+        let proof: Proof = serde_json::from_str(proof.value())?;
+
+        write_record(writer, &Record::SpentProof(proof))?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// Store the spent proofs of the backup at `input` in the database at
+/// `db_path`
+fn import_spent_proofs(db_path: &Path, input: &Path) -> anyhow::Result<usize> {
+    let db = Database::create(db_path)?;
+    let write_txn = db.begin_write()?;
+    let mut count = 0;
+
+    {
+        let mut table = write_txn.open_table(SPENT_PROOFS_TABLE)?;
+
+        for record in read_records(input)? {
+            if let Record::SpentProof(proof) = record? {
+                let y = proof.y()?.to_bytes();
+                table.insert(y, serde_json::to_string(&proof)?.as_str())?;
+                count += 1;
+            }
+        }
+    }
+
+    write_txn.commit()?;
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use cdk::nuts::{nut07, CheckStateRequest};
+
+    use super::*;
+    use crate::test_utils::{mint_on, mint_outputs, sat_keyset, spend, unblind, INVOICE};
+    use crate::utils::unix_time;
+
+    fn temp_path(extension: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "cashu-rs-mint-backup-test-{}.{}",
+            rand::random::<u64>(),
+            extension
+        ))
+    }
+
+    /// Lines of a backup in a stable order
+    fn sorted_lines(path: &Path) -> Vec<String> {
+        let mut lines: Vec<String> = std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        lines.sort();
+
+        lines
+    }
+
+    #[tokio::test]
+    async fn backup_round_trips_through_import() {
+        let source = temp_path("redb");
+
+        let spent = {
+            let localstore = open_localstore(&source).unwrap();
+            let mint = mint_on(localstore.clone()).await;
+
+            let (keyset_id, keys) = sat_keyset(&mint).await;
+            let proofs: Vec<Proof> = mint_outputs(&mint, &[8, 2])
+                .await
+                .iter()
+                .map(|(output, signature)| unblind(output, signature, &keys))
+                .collect();
+            spend(&mint, keyset_id, &proofs).await;
+
+            let quote = mint
+                .new_melt_quote(
+                    INVOICE.to_string(),
+                    CurrencyUnit::Sat,
+                    Amount::from(4),
+                    Amount::from(1),
+                    unix_time() + 60,
+                )
+                .await
+                .unwrap();
+            localstore
+                .add_melt_quote(MeltQuote {
+                    paid: true,
+                    ..quote
+                })
+                .await
+                .unwrap();
+
+            proofs
+        };
+
+        let backup = temp_path("jsonl");
+        export(&DatabaseEngine::Redb, &source, &backup)
+            .await
+            .unwrap();
+
+        let lines = sorted_lines(&backup);
+        let spent_records = lines
+            .iter()
+            .filter(|line| line.contains(r#""type":"spent_proof""#))
+            .count();
+        assert_eq!(spent_records, spent.len());
+        assert!(lines
+            .iter()
+            .any(|line| line.contains(r#""type":"unit_totals""#)));
+
+        let restored = temp_path("redb");
+        import(&DatabaseEngine::Redb, &restored, &backup)
+            .await
+            .unwrap();
+
+        let restored_backup = temp_path("jsonl");
+        export(&DatabaseEngine::Redb, &restored, &restored_backup)
+            .await
+            .unwrap();
+        assert_eq!(sorted_lines(&restored_backup), lines);
+
+        // A restored database is not empty anymore
+        assert!(import(&DatabaseEngine::Redb, &restored, &backup)
+            .await
+            .is_err());
+
+        // The mint on the restored database still knows the proofs as spent
+        let mint = mint_on(open_localstore(&restored).unwrap()).await;
+        let states = mint
+            .check_state(&CheckStateRequest {
+                ys: spent.iter().map(|proof| proof.y().unwrap()).collect(),
+            })
+            .await
+            .unwrap();
+        assert!(states
+            .states
+            .iter()
+            .all(|proof_state| proof_state.state == nut07::State::Spent));
+    }
+
+    #[tokio::test]
+    async fn sqlite_is_refused() {
+        let err = export(
+            &DatabaseEngine::Sqlite,
+            &temp_path("db"),
+            &temp_path("jsonl"),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(err.to_string().contains("redb"));
+    }
+}
@@ -1,4 +1,6 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(about = "A cashu mint written in rust", author = env!("CARGO_PKG_AUTHORS"), version = env!("CARGO_PKG_VERSION"))]
@@ -19,4 +21,29 @@ pub struct CLIArgs {
     pub config: Option<String>,
     #[arg(short, long, help = "Recover Greenlight from seed", required = false)]
     pub recover: Option<String>,
+    #[arg(long, help = "Write logs as JSON", required = false)]
+    pub json_logs: bool,
+    #[arg(
+        long,
+        help = "Print the mint pubkey, active keysets and supported nuts then exit",
+        required = false
+    )]
+    pub print_info: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Back up keysets, quotes and spent proofs to a JSON lines file (redb
+    /// databases only)
+    Export {
+        #[arg(short, long, help = "File to write the backup to")]
+        out: PathBuf,
+    },
+    /// Restore a file created by `export` into a fresh redb database
+    Import {
+        #[arg(short, long, help = "File to read the backup from")]
+        file: PathBuf,
+    },
 }
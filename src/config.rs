@@ -26,13 +26,20 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN
 THE SOFTWARE.
 */
 
+use std::net::{IpAddr, Ipv4Addr};
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
 use std::str::FromStr;
 
+use anyhow::bail;
+use bip39::Mnemonic;
+use cdk::nuts::CurrencyUnit;
 use cdk::Amount;
 use config::{Config, ConfigError, File};
 use serde::{Deserialize, Serialize};
-use tracing::{debug, warn};
+use url::Url;
+
+use crate::utils::{self, unit_derivation_path};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Info {
@@ -50,6 +57,72 @@ pub struct Info {
     pub max_order: u8,
     pub min_fee_reserve: Amount,
     pub min_fee_percent: f32,
+    /// Units with a keyset, sat and msat as there is no exchange rate for
+    /// fiat units
+    #[serde(default = "units_default")]
+    pub units: Vec<CurrencyUnit>,
+    pub min_mint_amount: Option<Amount>,
+    pub max_mint_amount: Option<Amount>,
+    pub max_melt_amount: Option<Amount>,
+    /// Requests per minute allowed from one IP on every endpoint
+    pub rate_limit_per_minute: Option<u32>,
+    /// Requests per minute allowed from one IP on the quote endpoints
+    pub quote_rate_limit_per_minute: Option<u32>,
+    /// Address of a reverse proxy whose `X-Forwarded-For` and `X-Real-IP`
+    /// headers are trusted for the client IP
+    pub trusted_proxy: Option<IpAddr>,
+    /// Max log level (trace, debug, info, warn, error), defaults to info
+    pub log_level: Option<String>,
+    /// PEM certificate, when set with `tls_key_path` the mint serves HTTPS
+    pub tls_cert_path: Option<PathBuf>,
+    /// PEM private key of `tls_cert_path`
+    pub tls_key_path: Option<PathBuf>,
+    /// Origins allowed by CORS, any origin is allowed when empty
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// Max size of a request body, axum defaults to 2MB
+    pub max_body_bytes: Option<usize>,
+    /// Max number of proofs in a swap or melt
+    pub max_inputs: Option<usize>,
+    /// Max number of blinded messages in a swap or melt
+    pub max_outputs: Option<usize>,
+    /// Max number of `Ys` in one check state request
+    pub max_check_state_batch: Option<usize>,
+    /// Max number of blinded messages in one restore request
+    pub max_restore_outputs: Option<usize>,
+    /// Number of restored signatures kept in memory, no cache when unset
+    pub restore_cache_size: Option<usize>,
+    /// Seconds a mint quote can be paid for, defaults to 30 minutes. Capped at
+    /// the expiry of its invoice
+    pub mint_quote_ttl_secs: Option<u64>,
+    /// Seconds a melt quote can be used for, defaults to 30 minutes
+    pub melt_quote_ttl_secs: Option<u64>,
+    /// Seconds between polls of the ln backend for payments of unpaid mint
+    /// quotes, in case the invoice stream missed them. Polling is off if unset
+    pub invoice_poll_interval_secs: Option<u64>,
+    /// Only poll quotes created within this many seconds, defaults to all
+    /// quotes that have not expired
+    pub invoice_poll_window_secs: Option<u64>,
+    /// Refuse mint quotes that would leave less than this ratio of ln balance
+    /// to outstanding ecash, melts are not affected
+    pub min_backing_ratio: Option<f64>,
+    /// Url notified of paid mint quotes and completed melts
+    pub webhook_url: Option<String>,
+    /// Key of the HMAC-SHA256 signature sent with webhooks
+    pub webhook_secret: Option<String>,
+}
+
+const MINT_QUOTE_TTL_DEFAULT: u64 = 1800;
+const MELT_QUOTE_TTL_DEFAULT: u64 = 1800;
+
+impl Info {
+    pub fn mint_quote_ttl(&self) -> u64 {
+        self.mint_quote_ttl_secs.unwrap_or(MINT_QUOTE_TTL_DEFAULT)
+    }
+
+    pub fn melt_quote_ttl(&self) -> u64 {
+        self.melt_quote_ttl_secs.unwrap_or(MELT_QUOTE_TTL_DEFAULT)
+    }
 }
 
 fn path_default() -> PathBuf {
@@ -60,10 +133,17 @@ fn derivation_path_default() -> String {
     "0/0/0/0".to_string()
 }
 
+/// Keysets have one key per power of two below `2^max_order`, amounts are u64
+pub const MAX_ORDER_RANGE: RangeInclusive<u8> = 1..=64;
+
 fn max_order_default() -> u8 {
     32
 }
 
+fn units_default() -> Vec<CurrencyUnit> {
+    vec![CurrencyUnit::Sat]
+}
+
 fn last_pay_path() -> String {
     "/tmp/config-rs-mint/last_path".to_string()
 }
@@ -83,8 +163,22 @@ pub struct Ln {
     pub cln_path: Option<PathBuf>,
     pub greenlight_invite_code: Option<String>,
     pub invoice_description: Option<String>,
+    /// Percent of a melt's amount reserved for the ln fee
     pub fee_percent: f64,
+    /// Smallest fee reserve of a melt in msat
     pub reserve_fee_min: Amount,
+    /// Outbound channel balance in sats a melt may not spend
+    pub outbound_reserve_sat: Option<u64>,
+}
+
+impl Ln {
+    /// Fee reserve in msat of paying `amount_msat`, the larger of
+    /// `reserve_fee_min` and `fee_percent` of the amount
+    pub fn fee_reserve_msat(&self, amount_msat: u64) -> u64 {
+        let percent_fee = (amount_msat as f64 * self.fee_percent / 100.0).ceil() as u64;
+
+        percent_fee.max(u64::from(self.reserve_fee_min))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
@@ -100,27 +194,58 @@ pub struct Database {
     pub engine: DatabaseEngine,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MintInfo {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub description_long: Option<String>,
+    pub motd: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Contact {
+    pub email: Option<String>,
+    pub nostr_npub: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Nuts {
+    /// Include DLEQ proofs (NUT-12) in blind signatures
+    pub nut12_enabled: bool,
+}
+
+impl Default for Nuts {
+    fn default() -> Self {
+        Self {
+            nut12_enabled: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Admin {
+    pub listen_host: String,
+    pub listen_port: u16,
+    /// Bearer token required on every admin request
+    pub api_key: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Settings {
     pub info: Info,
-    //    pub mint_info: MintInfo,
+    pub mint_info: MintInfo,
+    pub contact: Contact,
     pub ln: Ln,
     pub database: Database,
+    pub nuts: Nuts,
+    pub admin: Option<Admin>,
 }
 
 impl Settings {
-    #[must_use]
-    pub fn new(config_file_name: &Option<String>) -> Self {
-        let default_settings = Self::default();
-        // attempt to construct settings with file
-        let from_file = Self::new_from_default(&default_settings, config_file_name);
-        match from_file {
-            Ok(f) => f,
-            Err(e) => {
-                warn!("Error reading config file ({:?})", e);
-                default_settings
-            }
-        }
+    /// Read the settings from `config_file_name`, unset options keep their
+    /// default
+    pub fn new(config_file_name: &Option<String>) -> Result<Self, ConfigError> {
+        Self::new_from_default(&Self::default(), config_file_name)
     }
 
     fn new_from_default(
@@ -145,14 +270,283 @@ impl Settings {
             .build()?;
         let settings: Settings = config.try_deserialize()?;
 
-        debug!("{settings:?}");
+        Ok(settings)
+    }
+
+    /// Check the settings, returning every problem found in one error
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut errors = vec![];
 
-        match settings.ln.ln_backend {
-            LnBackend::Cln => assert!(settings.ln.cln_path.is_some()),
-            LnBackend::Greenlight => (),
-            LnBackend::Ldk => (),
+        if Url::parse(&self.info.url).is_err() {
+            errors.push(format!("info.url `{}` is not a valid url", self.info.url));
         }
 
-        Ok(settings)
+        if Ipv4Addr::from_str(&self.info.listen_host).is_err() {
+            errors.push(format!(
+                "info.listen_host `{}` is not an IPv4 address",
+                self.info.listen_host
+            ));
+        }
+
+        if Mnemonic::from_str(&self.info.mnemonic).is_err() {
+            errors.push("info.mnemonic is not a valid bip39 mnemonic".to_string());
+        }
+
+        if !MAX_ORDER_RANGE.contains(&self.info.max_order) {
+            errors.push(format!(
+                "info.max_order {} is not within {}..={}",
+                self.info.max_order,
+                MAX_ORDER_RANGE.start(),
+                MAX_ORDER_RANGE.end()
+            ));
+        }
+
+        if self.info.units.is_empty() {
+            errors.push("info.units must not be empty".to_string());
+        }
+
+        // Mint and melt quotes are bolt11 invoices, a unit has to convert to msat
+        for unit in &self.info.units {
+            if unit_derivation_path(unit, 0).is_none() || utils::to_msat(1, unit).is_none() {
+                errors.push(format!(
+                    "info.units: unit `{}` is not supported, only sat and msat",
+                    unit
+                ));
+            }
+        }
+
+        if let (Some(min), Some(max)) = (self.info.min_mint_amount, self.info.max_mint_amount) {
+            if min > max {
+                errors.push("info.min_mint_amount is above info.max_mint_amount".to_string());
+            }
+        }
+
+        if self.info.mint_quote_ttl_secs == Some(0) {
+            errors.push("info.mint_quote_ttl_secs must be above 0".to_string());
+        }
+
+        if self.info.melt_quote_ttl_secs == Some(0) {
+            errors.push("info.melt_quote_ttl_secs must be above 0".to_string());
+        }
+
+        if self.info.invoice_poll_interval_secs == Some(0) {
+            errors.push("info.invoice_poll_interval_secs must be above 0".to_string());
+        }
+
+        if let Some(ratio) = self.info.min_backing_ratio {
+            if !ratio.is_finite() || ratio <= 0.0 {
+                errors.push(format!(
+                    "info.min_backing_ratio {} must be a positive number",
+                    ratio
+                ));
+            }
+        }
+
+        if self.info.tls_cert_path.is_some() != self.info.tls_key_path.is_some() {
+            errors
+                .push("info.tls_cert_path and info.tls_key_path must be set together".to_string());
+        }
+
+        if let Some(log_level) = &self.info.log_level {
+            if tracing::Level::from_str(log_level).is_err() {
+                errors.push(format!("info.log_level `{}` is not a log level", log_level));
+            }
+        }
+
+        if let Some(webhook_url) = &self.info.webhook_url {
+            if Url::parse(webhook_url).is_err() {
+                errors.push(format!(
+                    "info.webhook_url `{}` is not a valid url",
+                    webhook_url
+                ));
+            }
+        }
+
+        match self.ln.ln_backend {
+            LnBackend::Cln => {
+                if self.ln.cln_path.is_none() {
+                    errors.push("ln.cln_path is required for the cln backend".to_string());
+                }
+            }
+            LnBackend::Greenlight | LnBackend::Ldk => {
+                errors.push(format!(
+                    "ln.ln_backend {:?} is not supported, use cln",
+                    self.ln.ln_backend
+                ));
+            }
+        }
+
+        if !(self.ln.fee_percent.is_finite() && self.ln.fee_percent >= 0.0) {
+            errors.push(format!(
+                "ln.fee_percent `{}` must be a number of at least 0",
+                self.ln.fee_percent
+            ));
+        }
+
+        // Browsers send `Origin` as scheme, host and port without a path
+        for origin in &self.info.allowed_origins {
+            if !Url::parse(origin).is_ok_and(|url| url.origin().ascii_serialization() == *origin) {
+                errors.push(format!(
+                    "info.allowed_origins: `{}` is not an origin like `https://example.com`",
+                    origin
+                ));
+            }
+        }
+
+        if let Some(admin) = &self.admin {
+            if admin.api_key.is_empty() {
+                errors.push("admin.api_key must be set to enable the admin API".to_string());
+            }
+
+            if Ipv4Addr::from_str(&admin.listen_host).is_err() {
+                errors.push(format!(
+                    "admin.listen_host `{}` is not an IPv4 address",
+                    admin.listen_host
+                ));
+            }
+
+            if admin.listen_port == self.info.listen_port {
+                errors.push("admin.listen_port must differ from info.listen_port".to_string());
+            }
+        }
+
+        if !errors.is_empty() {
+            bail!("Invalid config:\n  - {}", errors.join("\n  - "));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid_settings() -> Settings {
+        let mut settings = Settings::default();
+        settings.info.url = "https://example.com/".to_string();
+        settings.info.listen_host = "127.0.0.1".to_string();
+        settings.info.mnemonic = "inner strategy nasty verify camp bind avoid cause glue holiday dress bounce exotic husband hungry winter diet town spray team number hen upper seek".to_string();
+        settings.info.max_order = 32;
+        settings.info.units = units_default();
+        settings.ln.cln_path = Some(PathBuf::from("/tmp/lightning-rpc"));
+        settings
+    }
+
+    #[test]
+    fn valid_settings_pass() {
+        assert!(valid_settings().validate().is_ok());
+    }
+
+    #[test]
+    fn every_invalid_setting_is_reported() {
+        let mut settings = valid_settings();
+        settings.info.url = "not a url".to_string();
+        settings.info.max_order = 0;
+        settings.info.units = vec![CurrencyUnit::Usd];
+        settings.ln.cln_path = None;
+
+        let err = settings.validate().unwrap_err().to_string();
+
+        assert!(err.contains("info.url"));
+        assert!(err.contains("info.max_order"));
+        assert!(err.contains("info.units"));
+        assert!(err.contains("ln.cln_path"));
+        assert!(!err.contains("info.mnemonic"));
+    }
+
+    #[test]
+    fn max_order_bounds_are_inclusive() {
+        for (max_order, valid) in [(0, false), (1, true), (64, true), (65, false)] {
+            let mut settings = valid_settings();
+            settings.info.max_order = max_order;
+
+            assert_eq!(settings.validate().is_ok(), valid, "{}", max_order);
+        }
+    }
+
+    #[test]
+    fn allowed_origins_are_origins() {
+        let mut settings = valid_settings();
+        settings.info.allowed_origins = vec![
+            "https://example.com".to_string(),
+            "http://localhost:3000".to_string(),
+        ];
+        assert!(settings.validate().is_ok());
+
+        for origin in [
+            "https://example.com/",
+            "https://example.com/wallet",
+            "example.com",
+            "*",
+        ] {
+            settings.info.allowed_origins = vec![origin.to_string()];
+
+            let err = settings.validate().unwrap_err().to_string();
+            assert!(err.contains("info.allowed_origins"), "{}", origin);
+        }
+    }
+
+    #[test]
+    fn zero_fee_percent_is_allowed() {
+        let mut settings = valid_settings();
+        settings.ln.fee_percent = 0.0;
+        assert!(settings.validate().is_ok());
+
+        settings.ln.fee_percent = -1.0;
+        let err = settings.validate().unwrap_err().to_string();
+        assert!(err.contains("ln.fee_percent"));
+    }
+
+    #[test]
+    fn fiat_units_are_refused() {
+        let mut settings = valid_settings();
+        settings.info.units = vec![CurrencyUnit::Sat, CurrencyUnit::Usd];
+
+        let err = settings.validate().unwrap_err().to_string();
+
+        assert!(err.contains("info.units: unit `usd` is not supported"));
+
+        settings.info.units = vec![CurrencyUnit::Sat, CurrencyUnit::Msat];
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn units_are_required() {
+        let mut settings = valid_settings();
+        settings.info.units = vec![];
+
+        let err = settings.validate().unwrap_err().to_string();
+
+        assert!(err.contains("info.units must not be empty"));
+    }
+
+    #[test]
+    fn quote_ttls_must_be_positive() {
+        let mut settings = valid_settings();
+        settings.info.mint_quote_ttl_secs = Some(1);
+        settings.info.melt_quote_ttl_secs = Some(1);
+        assert!(settings.validate().is_ok());
+
+        settings.info.mint_quote_ttl_secs = Some(0);
+        settings.info.melt_quote_ttl_secs = Some(0);
+        let err = settings.validate().unwrap_err().to_string();
+
+        assert!(err.contains("info.mint_quote_ttl_secs"));
+        assert!(err.contains("info.melt_quote_ttl_secs"));
+    }
+
+    #[test]
+    fn fee_reserve_is_percent_with_minimum() {
+        let ln = Ln {
+            fee_percent: 1.0,
+            reserve_fee_min: Amount::from(1000),
+            ..Default::default()
+        };
+
+        assert_eq!(ln.fee_reserve_msat(10_000), 1000);
+        assert_eq!(ln.fee_reserve_msat(1_000_000), 10_000);
+        // Percent fees are rounded up to the next msat
+        assert_eq!(ln.fee_reserve_msat(1_000_050), 10_001);
     }
 }
@@ -0,0 +1,133 @@
+//! Quote queries over the mint database
+//!
+//! Keeps the HTTP handlers independent of the storage engine, everything here
+//! goes through the cdk `MintDatabase` so redb and sqlite behave the same.
+//! Quotes have no creation time so the most recent are taken to be the ones
+//! expiring last.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cdk::cdk_database::{self, MintDatabase};
+use cdk::nuts::CurrencyUnit;
+use cdk::types::{MeltQuote, MintQuote};
+use serde::{Deserialize, Serialize};
+
+use crate::utils;
+
+type Localstore = Arc<dyn MintDatabase<Err = cdk_database::Error> + Send + Sync>;
+
+/// One page of a query with the number of records across all pages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub total: usize,
+    pub items: Vec<T>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "quote", rename_all = "snake_case")]
+pub enum Quote {
+    Mint(MintQuote),
+    Melt(MeltQuote),
+}
+
+fn page<T>(mut items: Vec<T>, expiry: impl Fn(&T) -> u64, offset: usize, limit: usize) -> Page<T> {
+    items.sort_by_key(|item| std::cmp::Reverse(expiry(item)));
+
+    Page {
+        total: items.len(),
+        items: items.into_iter().skip(offset).take(limit).collect(),
+    }
+}
+
+pub async fn recent_mint_quotes(
+    localstore: &Localstore,
+    offset: usize,
+    limit: usize,
+) -> Result<Page<MintQuote>, cdk_database::Error> {
+    let quotes = localstore.get_mint_quotes().await?;
+
+    Ok(page(quotes, |quote| quote.expiry, offset, limit))
+}
+
+pub async fn recent_melt_quotes(
+    localstore: &Localstore,
+    offset: usize,
+    limit: usize,
+) -> Result<Page<MeltQuote>, cdk_database::Error> {
+    let quotes = localstore.get_melt_quotes().await?;
+
+    Ok(page(quotes, |quote| quote.expiry, offset, limit))
+}
+
+/// Mint or melt quote with `id`
+pub async fn quote_by_id(
+    localstore: &Localstore,
+    id: &str,
+) -> Result<Option<Quote>, cdk_database::Error> {
+    if let Some(quote) = localstore.get_mint_quote(id).await? {
+        return Ok(Some(Quote::Mint(quote)));
+    }
+
+    Ok(localstore.get_melt_quote(id).await?.map(Quote::Melt))
+}
+
+/// Amounts issued through paid mint quotes and redeemed through paid melt
+/// quotes, per unit
+///
+/// A melt counts its quoted amount. The routing fee it paid is not stored with
+/// the quote, so the ecash burnt for fees is still counted as outstanding.
+pub async fn unit_totals(
+    localstore: &Localstore,
+) -> Result<HashMap<CurrencyUnit, (u64, u64)>, cdk_database::Error> {
+    let mut totals: HashMap<CurrencyUnit, (u64, u64)> = HashMap::new();
+
+    for quote in localstore.get_mint_quotes().await? {
+        if quote.paid {
+            totals.entry(quote.unit).or_default().0 += u64::from(quote.amount);
+        }
+    }
+
+    for quote in localstore.get_melt_quotes().await? {
+        if quote.paid {
+            totals.entry(quote.unit).or_default().1 += u64::from(quote.amount);
+        }
+    }
+
+    Ok(totals)
+}
+
+/// Outstanding ecash of the bitcoin denominated units in msat
+pub fn outstanding_msat(totals: &HashMap<CurrencyUnit, (u64, u64)>) -> u64 {
+    totals
+        .iter()
+        .filter_map(|(unit, (issued, redeemed))| {
+            utils::to_msat(issued.saturating_sub(*redeemed), unit)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outstanding_is_issued_minus_redeemed() {
+        let totals = HashMap::from([
+            (CurrencyUnit::Sat, (1_000, 400)),
+            (CurrencyUnit::Msat, (5_000, 1_500)),
+            // No bitcoin amount, left out
+            (CurrencyUnit::Usd, (100, 0)),
+        ]);
+
+        assert_eq!(outstanding_msat(&totals), 600_000 + 3_500);
+    }
+
+    #[test]
+    fn more_redeemed_than_issued_is_nothing_outstanding() {
+        let totals = HashMap::from([(CurrencyUnit::Sat, (10, 20))]);
+
+        assert_eq!(outstanding_msat(&totals), 0);
+        assert_eq!(outstanding_msat(&HashMap::new()), 0);
+    }
+}
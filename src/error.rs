@@ -5,12 +5,65 @@ use axum::response::{IntoResponse, Response};
 use axum::Json;
 use cdk::error::ErrorResponse;
 use cdk::lightning_invoice::ParseOrSemanticError;
+use serde_json::json;
 
 #[derive(Debug)]
 pub enum Error {
     DecodeInvoice,
     StatusCode(StatusCode),
-    _Ln(ln_rs::Error),
+    Ln(anyhow::Error),
+    Mint(cdk::mint::error::Error),
+    /// Unit has no active keyset or can't be converted to msat
+    UnsupportedUnit,
+    /// Invoice does not specify an amount
+    InvoiceAmountUndefined,
+    /// Requested amount is outside of the configured limits
+    AmountOutsideLimit {
+        amount: u64,
+        min: Option<u64>,
+        max: Option<u64>,
+    },
+    /// Quote is past its expiry
+    QuoteExpired,
+    /// Invoice of the quote has not been paid
+    QuoteNotPaid,
+    /// Invoice of the quote has already been paid
+    InvoiceAlreadyPaid,
+    /// Invoice of the quote can still be paid
+    InvoiceNotExpired,
+    /// Ln backend could not create an invoice
+    InvoiceUnavailable,
+    /// Mint is paused by the operator
+    Maintenance,
+    /// Mint is paused by the operator, returned where minting is refused so
+    /// it gets the NUT-04 code
+    MintingDisabled,
+    /// Paying would take outbound liquidity below the reserve
+    InsufficientLiquidity,
+    /// Minting would take the backing ratio below the minimum
+    InsufficientBacking,
+    /// Proofs are used by a melt in progress
+    ProofsPending,
+    /// Melt quote is being paid by another request
+    QuotePending,
+    /// Keyset id is malformed or not one of the mint's keysets
+    UnknownKeyset,
+    /// Ln backend did not return a preimage for the payment
+    PaymentFailed,
+    /// Request has more inputs than allowed
+    TooManyInputs {
+        max: usize,
+    },
+    /// Request has more outputs than allowed
+    TooManyOutputs {
+        max: usize,
+    },
+    /// Check state request has more `Ys` than allowed
+    TooManyYs {
+        max: usize,
+    },
+    /// Idempotency key was used before with a different request
+    IdempotencyKeyReused,
 }
 
 impl std::error::Error for Error {}
@@ -20,11 +73,66 @@ impl fmt::Display for Error {
         match self {
             Self::DecodeInvoice => write!(f, "Failed to decode LN Invoice"),
             Self::StatusCode(code) => write!(f, "{}", code),
-            Self::_Ln(code) => write!(f, "{}", code),
+            Self::Ln(code) => write!(f, "{}", code),
+            Self::Mint(err) => write!(f, "{}", err),
+            Self::UnsupportedUnit => write!(f, "Unit unsupported"),
+            Self::InvoiceAmountUndefined => write!(f, "Invoice amount undefined"),
+            Self::AmountOutsideLimit { amount, min, max } => write!(
+                f,
+                "Amount {} outside of limit (min: {:?}, max: {:?})",
+                amount, min, max
+            ),
+            Self::QuoteExpired => write!(f, "Quote expired"),
+            Self::QuoteNotPaid => write!(f, "Quote not paid"),
+            Self::InvoiceAlreadyPaid => write!(f, "Invoice already paid"),
+            Self::InvoiceNotExpired => write!(f, "Invoice has not expired"),
+            Self::ProofsPending => write!(f, "Proofs are pending in another request"),
+            Self::QuotePending => write!(f, "Quote is pending"),
+            Self::InsufficientLiquidity => {
+                write!(f, "Mint does not have enough outbound liquidity")
+            }
+            Self::InsufficientBacking => {
+                write!(f, "Mint is at its issuance limit, try again later")
+            }
+            Self::Maintenance => write!(f, "Mint in maintenance, try again later"),
+            Self::MintingDisabled => write!(f, "Minting is disabled, try again later"),
+            Self::UnknownKeyset => write!(f, "Keyset is not known"),
+            Self::InvoiceUnavailable => write!(f, "Could not create invoice, try again later"),
+            Self::PaymentFailed => write!(f, "Lightning payment failed"),
+            Self::TooManyInputs { max } => write!(f, "Too many inputs, max is {}", max),
+            Self::TooManyOutputs { max } => write!(f, "Too many outputs, max is {}", max),
+            Self::TooManyYs { max } => write!(f, "Too many Ys, max is {}", max),
+            Self::IdempotencyKeyReused => {
+                write!(f, "Idempotency key was used for a different request")
+            }
+        }
+    }
+}
+
+impl Error {
+    /// Cashu error code (NUT-00) of errors that have one
+    fn code(&self) -> Option<u32> {
+        match self {
+            Self::UnsupportedUnit => Some(11005),
+            Self::AmountOutsideLimit { .. } => Some(11006),
+            Self::UnknownKeyset => Some(12001),
+            Self::QuoteNotPaid => Some(20001),
+            Self::MintingDisabled => Some(20003),
+            Self::PaymentFailed => Some(20004),
+            Self::QuotePending => Some(20005),
+            Self::InvoiceAlreadyPaid => Some(20006),
+            Self::QuoteExpired => Some(20007),
+            _ => None,
         }
     }
 }
 
+impl From<cdk::mint::error::Error> for Error {
+    fn from(err: cdk::mint::error::Error) -> Self {
+        Self::Mint(err)
+    }
+}
+
 impl From<StatusCode> for Error {
     fn from(code: StatusCode) -> Self {
         Self::StatusCode(code)
@@ -45,12 +153,53 @@ impl From<url::ParseError> for Error {
 
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
-        match self {
-            Error::DecodeInvoice => (StatusCode::BAD_REQUEST, self.to_string()).into_response(),
-            Error::StatusCode(code) => (code, "").into_response(),
-            Error::_Ln(code) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, code.to_string()).into_response()
+        let status = match self {
+            Error::DecodeInvoice => {
+                return (StatusCode::BAD_REQUEST, self.to_string()).into_response()
             }
+            Error::StatusCode(code) => return (code, "").into_response(),
+            Error::Ln(code) => {
+                return (StatusCode::INTERNAL_SERVER_ERROR, code.to_string()).into_response()
+            }
+            Error::Mint(err) => return into_response(err),
+            Error::InvoiceUnavailable | Error::Maintenance | Error::MintingDisabled => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            Error::UnknownKeyset => StatusCode::NOT_FOUND,
+            Error::IdempotencyKeyReused => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::UnsupportedUnit
+            | Error::InvoiceAmountUndefined
+            | Error::AmountOutsideLimit { .. }
+            | Error::QuoteExpired
+            | Error::QuoteNotPaid
+            | Error::InvoiceAlreadyPaid
+            | Error::InvoiceNotExpired
+            | Error::ProofsPending
+            | Error::QuotePending
+            | Error::InsufficientLiquidity
+            | Error::InsufficientBacking
+            | Error::PaymentFailed
+            | Error::TooManyInputs { .. }
+            | Error::TooManyOutputs { .. }
+            | Error::TooManyYs { .. } => StatusCode::BAD_REQUEST,
+        };
+
+        (status, Json(self.body())).into_response()
+    }
+}
+
+impl Error {
+    /// Json body of the response
+    fn body(&self) -> serde_json::Value {
+        // Errors without a NUT-00 code leave it out rather than send a made up one
+        match self.code() {
+            Some(code) => json!({
+                "code": code,
+                "detail": self.to_string(),
+            }),
+            None => json!({
+                "detail": self.to_string(),
+            }),
         }
     }
 }
@@ -62,3 +211,61 @@ pub fn into_response(error: cdk::mint::error::Error) -> Response {
     )
         .into_response()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maintenance_has_a_code_only_where_minting_is_refused() {
+        assert_eq!(Error::MintingDisabled.code(), Some(20003));
+        assert_eq!(Error::Maintenance.code(), None);
+
+        for error in [Error::MintingDisabled, Error::Maintenance] {
+            assert_eq!(
+                error.into_response().status(),
+                StatusCode::SERVICE_UNAVAILABLE
+            );
+        }
+
+        assert_eq!(
+            Error::MintingDisabled.body(),
+            json!({
+                "code": 20003,
+                "detail": "Minting is disabled, try again later",
+            })
+        );
+        assert_eq!(
+            Error::Maintenance.body(),
+            json!({
+                "detail": "Mint in maintenance, try again later",
+            })
+        );
+    }
+
+    #[test]
+    fn errors_have_their_nut_codes() {
+        let cases = [
+            (Error::UnsupportedUnit, 11005),
+            (
+                Error::AmountOutsideLimit {
+                    amount: 1,
+                    min: Some(2),
+                    max: None,
+                },
+                11006,
+            ),
+            (Error::UnknownKeyset, 12001),
+            (Error::QuoteNotPaid, 20001),
+            (Error::PaymentFailed, 20004),
+            (Error::QuotePending, 20005),
+            (Error::InvoiceAlreadyPaid, 20006),
+            (Error::QuoteExpired, 20007),
+        ];
+
+        for (error, code) in cases {
+            assert_eq!(error.body()["code"], code, "{}", error);
+            assert_eq!(error.into_response().status(), StatusCode::BAD_REQUEST);
+        }
+    }
+}
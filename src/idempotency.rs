@@ -0,0 +1,193 @@
+//! Idempotency keys for mint quote creation
+//!
+//! A wallet retrying `POST /v1/mint/quote/bolt11` with the same
+//! `Idempotency-Key` header gets the quote created by the first request back
+//! instead of a new invoice. Keys are stored in their own database next to the
+//! mint database, so a retry after a restart is answered the same, and kept
+//! until the quote expires.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use redb::{Database, ReadableTable, TableDefinition};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+use crate::utils::unix_time;
+
+/// Header carrying the idempotency key of a request
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+/// Json encoded [`Entry`] by idempotency key
+const KEYS_TABLE: TableDefinition<&str, &str> = TableDefinition::new("idempotency_keys");
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub quote_id: String,
+    /// Json encoded request the quote was created for
+    pub request: String,
+    pub expiry: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct IdempotencyKeys {
+    db: Arc<Database>,
+    locks: Arc<std::sync::Mutex<HashMap<String, Arc<Mutex<()>>>>>,
+}
+
+impl IdempotencyKeys {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let db = Database::create(path)?;
+
+        let write_txn = db.begin_write()?;
+        write_txn.open_table(KEYS_TABLE)?;
+        write_txn.commit()?;
+
+        Ok(Self {
+            db: Arc::new(db),
+            locks: Arc::default(),
+        })
+    }
+
+    /// Hold the guard until the quote is stored so a concurrent retry with
+    /// the same key waits for it rather than creating a second invoice
+    pub async fn lock(&self, key: &str) -> OwnedMutexGuard<()> {
+        let lock = self
+            .locks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(key.to_string())
+            .or_default()
+            .clone();
+
+        lock.lock_owned().await
+    }
+
+    /// Entry of `key` if its quote has not expired
+    pub fn get(&self, key: &str) -> anyhow::Result<Option<Entry>> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(KEYS_TABLE)?;
+
+        let entry = match table.get(key)? {
+            Some(value) => serde_json::from_str::<Entry>(value.value())?,
+            None => return Ok(None),
+        };
+
+        Ok(Some(entry).filter(|entry| entry.expiry >= unix_time()))
+    }
+
+    pub fn insert(&self, key: &str, entry: &Entry) -> anyhow::Result<()> {
+        let value = serde_json::to_string(entry)?;
+
+        let write_txn = self.db.begin_write()?;
+        {
+            let mut table = write_txn.open_table(KEYS_TABLE)?;
+            table.insert(key, value.as_str())?;
+        }
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    /// Remove the keys of expired quotes and the locks no request is holding,
+    /// returning the number of keys removed
+    pub fn prune(&self) -> anyhow::Result<usize> {
+        let now = unix_time();
+
+        let write_txn = self.db.begin_write()?;
+        let expired = {
+            let mut table = write_txn.open_table(KEYS_TABLE)?;
+
+            let mut expired = vec![];
+            for item in table.iter()? {
+                let (key, value) = item?;
+                match serde_json::from_str::<Entry>(value.value()) {
+                    Ok(entry) if entry.expiry >= now => (),
+                    _ => expired.push(key.value().to_string()),
+                }
+            }
+
+            for key in &expired {
+                table.remove(key.as_str())?;
+            }
+
+            expired.len()
+        };
+        write_txn.commit()?;
+
+        self.locks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|_, lock| Arc::strong_count(lock) > 1);
+
+        Ok(expired)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn temp_keys() -> (IdempotencyKeys, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!(
+            "cashu-rs-mint-idempotency-test-{}.redb",
+            rand::random::<u64>()
+        ));
+
+        (IdempotencyKeys::open(&path).unwrap(), path)
+    }
+
+    fn entry(quote_id: &str, expiry: u64) -> Entry {
+        Entry {
+            quote_id: quote_id.to_string(),
+            request: r#"{"amount":10,"unit":"sat"}"#.to_string(),
+            expiry,
+        }
+    }
+
+    #[tokio::test]
+    async fn retry_gets_the_same_quote() {
+        let (keys, path) = temp_keys();
+
+        let stored = entry("quote", unix_time() + 60);
+
+        {
+            let first = keys.lock("key").await;
+            assert!(keys.get("key").unwrap().is_none());
+            keys.insert("key", &stored).unwrap();
+
+            // A concurrent retry waits for the first request to store its quote
+            let retry = keys.lock("key");
+            tokio::pin!(retry);
+            assert!(tokio::time::timeout(Duration::from_millis(50), &mut retry)
+                .await
+                .is_err());
+            drop(first);
+            let _retry = retry.await;
+        }
+
+        assert_eq!(keys.get("key").unwrap(), Some(stored.clone()));
+
+        // Keys survive a restart
+        drop(keys);
+        let keys = IdempotencyKeys::open(&path).unwrap();
+        assert_eq!(keys.get("key").unwrap(), Some(stored));
+    }
+
+    #[test]
+    fn expired_keys_are_ignored_and_pruned() {
+        let (keys, _) = temp_keys();
+
+        keys.insert("expired", &entry("old", unix_time() - 1))
+            .unwrap();
+        keys.insert("current", &entry("new", unix_time() + 60))
+            .unwrap();
+
+        assert!(keys.get("expired").unwrap().is_none());
+        assert_eq!(keys.prune().unwrap(), 1);
+        assert_eq!(keys.get("current").unwrap().unwrap().quote_id, "new");
+    }
+}
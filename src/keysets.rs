@@ -0,0 +1,171 @@
+//! Creation and rotation of the keysets of the configured units
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use cdk::cdk_database::{self, MintDatabase};
+use cdk::mint::{Mint, MintKeySetInfo};
+use cdk::nuts::{CurrencyUnit, Id};
+use tracing::info;
+
+use crate::error::Error;
+use crate::utils::{self, unix_time};
+
+type Localstore = Arc<dyn MintDatabase<Err = cdk_database::Error> + Send + Sync>;
+
+/// Replace the active keyset of `unit` with a new one of `max_order` keys
+///
+/// The replaced keyset is stamped with `valid_to` and is still accepted in
+/// swaps and melts. Returns the id of the new keyset.
+pub async fn rotate_keyset(
+    mint: &Mint,
+    localstore: &Localstore,
+    unit: &CurrencyUnit,
+    max_order: u8,
+) -> Result<Id, Error> {
+    let active_id = localstore
+        .get_active_keyset_id(unit)
+        .await
+        .map_err(|err| Error::Mint(err.into()))?
+        .ok_or(Error::UnsupportedUnit)?;
+
+    let active = localstore
+        .get_keyset_info(&active_id)
+        .await
+        .map_err(|err| Error::Mint(err.into()))?
+        .ok_or(Error::UnsupportedUnit)?;
+
+    let derivation_path = utils::next_derivation_path(unit, &active.derivation_path)
+        .ok_or(Error::StatusCode(StatusCode::INTERNAL_SERVER_ERROR))?;
+
+    mint.rotate_keyset(unit.clone(), derivation_path, max_order)
+        .await?;
+
+    localstore
+        .add_keyset_info(MintKeySetInfo {
+            active: false,
+            valid_to: Some(unix_time()),
+            ..active
+        })
+        .await
+        .map_err(|err| Error::Mint(err.into()))?;
+
+    let new_id = localstore
+        .get_active_keyset_id(unit)
+        .await
+        .map_err(|err| Error::Mint(err.into()))?
+        .ok_or(Error::UnsupportedUnit)?;
+
+    info!("Rotated {} keyset {} to {}", unit, active_id, new_id);
+
+    Ok(new_id)
+}
+
+/// Give every unit of `units` an active keyset of `max_order` keys
+///
+/// Units without an active keyset get their first one. An active keyset with
+/// another max order is rotated, this includes the sat keyset `Mint::new`
+/// creates with 64 keys whatever the config says.
+pub async fn ensure_keysets(
+    mint: &Mint,
+    localstore: &Localstore,
+    units: &[CurrencyUnit],
+    max_order: u8,
+) -> anyhow::Result<()> {
+    for unit in units {
+        let active_id = match localstore.get_active_keyset_id(unit).await? {
+            Some(active_id) => active_id,
+            None => {
+                let derivation_path = utils::unit_derivation_path(unit, 0)
+                    .ok_or(anyhow::anyhow!("No derivation path for unit {}", unit))?;
+
+                mint.rotate_keyset(unit.clone(), derivation_path, max_order)
+                    .await?;
+                continue;
+            }
+        };
+
+        let active = localstore
+            .get_keyset_info(&active_id)
+            .await?
+            .ok_or(anyhow::anyhow!("Active keyset {} is not stored", active_id))?;
+
+        if active.max_order != max_order {
+            info!(
+                "Active {} keyset {} has max order {}, configured is {}",
+                unit, active_id, active.max_order, max_order
+            );
+            rotate_keyset(mint, localstore, unit, max_order).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{mint_on, test_localstore};
+
+    /// Number of keys of the active keyset of `unit`
+    async fn active_key_count(mint: &Mint, localstore: &Localstore, unit: &CurrencyUnit) -> usize {
+        let id = localstore
+            .get_active_keyset_id(unit)
+            .await
+            .unwrap()
+            .unwrap();
+        let pubkeys = serde_json::to_value(mint.keyset_pubkeys(&id).await.unwrap()).unwrap();
+
+        pubkeys["keysets"][0]["keys"].as_object().unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn keysets_have_max_order_keys() {
+        let units = [CurrencyUnit::Sat, CurrencyUnit::Msat];
+
+        for max_order in [1, 8, 64] {
+            let localstore = test_localstore();
+            let mint = mint_on(localstore.clone()).await;
+
+            ensure_keysets(&mint, &localstore, &units, max_order)
+                .await
+                .unwrap();
+
+            for unit in &units {
+                assert_eq!(
+                    active_key_count(&mint, &localstore, unit).await,
+                    max_order as usize,
+                    "{} {}",
+                    unit,
+                    max_order
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn keysets_of_the_configured_max_order_are_kept() {
+        let localstore = test_localstore();
+        let mint = mint_on(localstore.clone()).await;
+
+        ensure_keysets(&mint, &localstore, &[CurrencyUnit::Sat], 8)
+            .await
+            .unwrap();
+        let active = localstore.get_active_keysets().await.unwrap();
+
+        ensure_keysets(&mint, &localstore, &[CurrencyUnit::Sat], 8)
+            .await
+            .unwrap();
+        assert_eq!(localstore.get_active_keysets().await.unwrap(), active);
+
+        // A new max order replaces the keyset
+        ensure_keysets(&mint, &localstore, &[CurrencyUnit::Sat], 16)
+            .await
+            .unwrap();
+        assert_ne!(localstore.get_active_keysets().await.unwrap(), active);
+        assert_eq!(
+            active_key_count(&mint, &localstore, &CurrencyUnit::Sat).await,
+            16
+        );
+    }
+}
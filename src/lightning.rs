@@ -0,0 +1,101 @@
+//! Lightning node the mint creates, watches and pays invoices with
+//!
+//! Handlers and background tasks take a [`Lightning`] rather than the ln-rs
+//! node so they can be run against a mock node in tests.
+
+use std::pin::Pin;
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use ln_rs::{Bolt11Invoice, Cln, InvoiceStatus, LnNodeManager, LnProcessor};
+
+/// Paid invoices with the pay index to resume the stream from
+pub type InvoiceStream = Pin<Box<dyn Stream<Item = (Bolt11Invoice, Option<u64>)> + Send>>;
+
+/// Outcome of paying an invoice
+#[derive(Debug, Clone)]
+pub struct Payment {
+    /// Missing if the payment did not complete
+    pub preimage: Option<String>,
+    /// Amount and routing fees paid
+    pub total_spent_msat: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Balance {
+    /// Outbound balance of the channels
+    pub ln_msat: u64,
+    pub on_chain_sat: u64,
+}
+
+#[async_trait]
+pub trait Lightning: Send + Sync {
+    async fn create_invoice(
+        &self,
+        amount_msat: u64,
+        description: String,
+    ) -> anyhow::Result<Bolt11Invoice>;
+
+    async fn invoice_status(&self, invoice: &Bolt11Invoice) -> anyhow::Result<InvoiceStatus>;
+
+    /// Subscribe to invoices as they are paid
+    async fn wait_invoice(&self) -> anyhow::Result<InvoiceStream>;
+
+    async fn pay_invoice(
+        &self,
+        invoice: Bolt11Invoice,
+        max_fee_msat: u64,
+    ) -> anyhow::Result<Payment>;
+
+    async fn balance(&self) -> anyhow::Result<Balance>;
+}
+
+#[async_trait]
+impl Lightning for Cln {
+    async fn create_invoice(
+        &self,
+        amount_msat: u64,
+        description: String,
+    ) -> anyhow::Result<Bolt11Invoice> {
+        Ok(
+            LnProcessor::create_invoice(self, ln_rs::Amount::from_msat(amount_msat), description)
+                .await?,
+        )
+    }
+
+    async fn invoice_status(&self, invoice: &Bolt11Invoice) -> anyhow::Result<InvoiceStatus> {
+        Ok(LnProcessor::check_invoice_status(self, invoice.payment_hash()).await?)
+    }
+
+    async fn wait_invoice(&self) -> anyhow::Result<InvoiceStream> {
+        Ok(LnProcessor::wait_invoice(self).await?.boxed())
+    }
+
+    async fn pay_invoice(
+        &self,
+        invoice: Bolt11Invoice,
+        max_fee_msat: u64,
+    ) -> anyhow::Result<Payment> {
+        let payment = LnProcessor::pay_invoice(
+            self,
+            invoice,
+            None,
+            Some(ln_rs::Amount::from_msat(max_fee_msat)),
+        )
+        .await?;
+
+        Ok(Payment {
+            preimage: payment.payment_preimage,
+            total_spent_msat: payment.total_spent.to_msat(),
+        })
+    }
+
+    async fn balance(&self) -> anyhow::Result<Balance> {
+        let balance = LnNodeManager::get_balance(self).await?;
+
+        Ok(Balance {
+            ln_msat: balance.ln.to_msat(),
+            on_chain_sat: balance.on_chain_spendable.to_sat(),
+        })
+    }
+}
@@ -0,0 +1,66 @@
+//! Maintenance mode
+//!
+//! While paused the mint keeps serving keys and info but refuses new quotes,
+//! mints, melts and swaps. The flag is a marker file so a restart during
+//! maintenance stays paused.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use axum::response::{IntoResponse, Response};
+
+use crate::error::Error;
+
+#[derive(Debug, Clone)]
+pub struct Maintenance {
+    paused: Arc<AtomicBool>,
+    path: PathBuf,
+}
+
+impl Maintenance {
+    /// Read the flag from the marker file at `path`
+    pub fn load(path: PathBuf) -> Self {
+        Self {
+            paused: Arc::new(AtomicBool::new(path.exists())),
+            path,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn set_paused(&self, paused: bool) -> std::io::Result<()> {
+        match paused {
+            true => fs::write(&self.path, b"")?,
+            false => {
+                if self.path.exists() {
+                    fs::remove_file(&self.path)?;
+                }
+            }
+        }
+
+        self.paused.store(paused, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    /// Error with `503 Service Unavailable` while paused
+    pub fn check(&self) -> Result<(), Response> {
+        match self.is_paused() {
+            true => Err(Error::Maintenance.into_response()),
+            false => Ok(()),
+        }
+    }
+
+    /// Like [`Self::check`] but with the "minting is disabled" code, for mint
+    /// quotes and mints
+    pub fn check_minting(&self) -> Result<(), Response> {
+        match self.is_paused() {
+            true => Err(Error::MintingDisabled.into_response()),
+            false => Ok(()),
+        }
+    }
+}
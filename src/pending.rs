@@ -0,0 +1,104 @@
+//! Proofs and quotes reserved by in flight melts
+//!
+//! A melt releases the mint lock while the invoice is paid, so without a
+//! reservation two melts with the same proofs or the same quote can both pass
+//! verification and both pay.
+
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+use cdk::nuts::PublicKey;
+
+/// Ys of proofs used by a melt in progress
+pub type PendingProofs = Pending<PublicKey>;
+
+/// Ids of melt quotes being paid
+pub type PendingQuotes = Pending<String>;
+
+#[derive(Debug)]
+pub struct Pending<T> {
+    items: Arc<Mutex<HashSet<T>>>,
+}
+
+impl<T> Clone for Pending<T> {
+    fn clone(&self) -> Self {
+        Self {
+            items: self.items.clone(),
+        }
+    }
+}
+
+impl<T> Default for Pending<T> {
+    fn default() -> Self {
+        Self {
+            items: Arc::default(),
+        }
+    }
+}
+
+impl<T: Eq + Hash + Clone> Pending<T> {
+    /// Reserve `items`, returns `None` if any of them is already reserved
+    ///
+    /// The items stay reserved until the returned [`Reservation`] is dropped.
+    pub fn reserve(&self, items: Vec<T>) -> Option<Reservation<T>> {
+        let mut pending = self.items.lock().unwrap_or_else(|e| e.into_inner());
+
+        let unique: HashSet<_> = items.iter().collect();
+        if unique.len() != items.len() || items.iter().any(|item| pending.contains(item)) {
+            return None;
+        }
+
+        pending.extend(items.iter().cloned());
+
+        Some(Reservation {
+            pending: self.clone(),
+            items,
+        })
+    }
+}
+
+pub struct Reservation<T: Eq + Hash> {
+    pending: Pending<T>,
+    items: Vec<T>,
+}
+
+impl<T: Eq + Hash> Drop for Reservation<T> {
+    fn drop(&mut self) {
+        let mut pending = self.pending.items.lock().unwrap_or_else(|e| e.into_inner());
+
+        for item in &self.items {
+            pending.remove(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserved_items_are_refused_until_dropped() {
+        let pending = PendingQuotes::default();
+
+        let reservation = pending.reserve(vec!["quote".to_string()]);
+        assert!(reservation.is_some());
+        assert!(pending.reserve(vec!["quote".to_string()]).is_none());
+        assert!(pending
+            .reserve(vec!["other".to_string(), "quote".to_string()])
+            .is_none());
+
+        drop(reservation);
+        assert!(pending.reserve(vec!["quote".to_string()]).is_some());
+    }
+
+    #[test]
+    fn duplicate_items_are_refused() {
+        let pending = PendingQuotes::default();
+
+        assert!(pending
+            .reserve(vec!["quote".to_string(), "quote".to_string()])
+            .is_none());
+        assert!(pending.reserve(vec!["quote".to_string()]).is_some());
+    }
+}
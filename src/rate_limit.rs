@@ -0,0 +1,159 @@
+//! Per client IP rate limiting of the mint endpoints
+
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, State};
+use axum::http::{HeaderMap, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use tracing::debug;
+
+/// Number of tracked clients after which expired windows are pruned
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Fixed window request counter per client IP
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    clients: Arc<Mutex<HashMap<IpAddr, (Instant, u32)>>>,
+    trusted_proxy: Option<IpAddr>,
+}
+
+impl RateLimiter {
+    pub fn per_minute(limit: u32) -> Self {
+        Self {
+            limit,
+            window: Duration::from_secs(60),
+            clients: Arc::new(Mutex::new(HashMap::new())),
+            trusted_proxy: None,
+        }
+    }
+
+    /// Take the client IP from proxy headers on requests from `trusted_proxy`
+    pub fn with_trusted_proxy(mut self, trusted_proxy: Option<IpAddr>) -> Self {
+        self.trusted_proxy = trusted_proxy;
+        self
+    }
+
+    /// Count a request from `ip`, returns `false` if it is over the limit
+    pub fn check(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+
+        if clients.len() > PRUNE_THRESHOLD {
+            clients.retain(|_, (start, _)| now.duration_since(*start) < self.window);
+        }
+
+        let (start, count) = clients.entry(ip).or_insert((now, 0));
+
+        if now.duration_since(*start) >= self.window {
+            *start = now;
+            *count = 0;
+        }
+
+        *count += 1;
+
+        *count <= self.limit
+    }
+}
+
+/// Middleware rejecting requests over the limit with `429 Too Many Requests`
+pub async fn rate_limit<B>(
+    State(limiter): State<RateLimiter>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let ip = client_ip(limiter.trusted_proxy, addr.ip(), request.headers());
+
+    if !limiter.check(ip) {
+        debug!("Rate limited {}", ip);
+        return StatusCode::TOO_MANY_REQUESTS.into_response();
+    }
+
+    next.run(request).await
+}
+
+/// IP of the client that sent a request received from `peer`
+///
+/// `X-Forwarded-For` and `X-Real-IP` can be set by anyone so they are only
+/// used when `peer` is the trusted proxy. The last `X-Forwarded-For` entry is
+/// the one the proxy appended.
+fn client_ip(trusted_proxy: Option<IpAddr>, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+    if trusted_proxy != Some(peer) {
+        return peer;
+    }
+
+    let forwarded_for = headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.rsplit(',').next())
+        .and_then(|ip| ip.trim().parse().ok());
+
+    let real_ip = || {
+        headers
+            .get("x-real-ip")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|ip| ip.trim().parse().ok())
+    };
+
+    forwarded_for.or_else(real_ip).unwrap_or(peer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn request_over_limit_is_rejected() {
+        let limiter = RateLimiter::per_minute(3);
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+        for _ in 0..3 {
+            assert!(limiter.check(ip));
+        }
+        assert!(!limiter.check(ip));
+
+        // Other clients have their own window
+        assert!(limiter.check(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))));
+    }
+
+    fn proxy_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "1.1.1.1, 2.2.2.2".parse().unwrap());
+        headers.insert("x-real-ip", "3.3.3.3".parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn proxy_headers_are_used_from_trusted_proxy() {
+        let proxy = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        assert_eq!(
+            client_ip(Some(proxy), proxy, &proxy_headers()),
+            IpAddr::V4(Ipv4Addr::new(2, 2, 2, 2))
+        );
+
+        let mut headers = proxy_headers();
+        headers.remove("x-forwarded-for");
+        assert_eq!(
+            client_ip(Some(proxy), proxy, &headers),
+            IpAddr::V4(Ipv4Addr::new(3, 3, 3, 3))
+        );
+    }
+
+    #[test]
+    fn proxy_headers_are_ignored_from_other_peers() {
+        let proxy = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let peer = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+        assert_eq!(client_ip(Some(proxy), peer, &proxy_headers()), peer);
+        assert_eq!(client_ip(None, peer, &proxy_headers()), peer);
+    }
+}
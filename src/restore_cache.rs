@@ -0,0 +1,43 @@
+//! Cache of signatures returned by restore
+//!
+//! A blind signature never changes once issued, so wallets restoring in
+//! overlapping batches can be answered from memory. The least recently used
+//! entry is evicted once the cache is full.
+
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+
+use cdk::nuts::{BlindSignature, PublicKey};
+use lru::LruCache;
+
+#[derive(Debug, Clone)]
+pub struct RestoreCache {
+    /// `None` when the cache size is 0
+    entries: Option<Arc<Mutex<LruCache<PublicKey, BlindSignature>>>>,
+}
+
+impl RestoreCache {
+    pub fn new(size: usize) -> Self {
+        Self {
+            entries: NonZeroUsize::new(size).map(|size| Arc::new(Mutex::new(LruCache::new(size)))),
+        }
+    }
+
+    pub fn get(&self, blinded_secret: &PublicKey) -> Option<BlindSignature> {
+        self.entries
+            .as_ref()?
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(blinded_secret)
+            .cloned()
+    }
+
+    pub fn insert(&self, blinded_secret: PublicKey, signature: BlindSignature) {
+        if let Some(entries) = &self.entries {
+            entries
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .put(blinded_secret, signature);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Restarting of background tasks
+//!
+//! A panic in a spawned task only ends that task, so without this the mint
+//! would keep serving requests with, say, no invoice watcher.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use tracing::{error, warn};
+
+/// Bounds of the delay before restarting a stopped task
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Run the task made by `task`, making a new one whenever it stops or panics
+pub fn supervise<F, Fut>(name: &'static str, task: F)
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut backoff = MIN_BACKOFF;
+
+        loop {
+            let started = Instant::now();
+
+            match tokio::spawn(task()).await {
+                Ok(()) => warn!("Task {} stopped", name),
+                Err(err) if err.is_panic() => error!("Task {} panicked", name),
+                Err(_) => return,
+            }
+
+            // A task that ran for a while before stopping starts over quickly
+            if started.elapsed() > MAX_BACKOFF {
+                backoff = MIN_BACKOFF;
+            }
+
+            warn!("Restarting task {} in {:?}", name, backoff);
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn panicking_task_is_restarted() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let task_runs = runs.clone();
+
+        supervise("test", move || {
+            let runs = task_runs.clone();
+
+            async move {
+                if runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                    panic!("first run panics");
+                }
+
+                std::future::pending::<()>().await
+            }
+        });
+
+        tokio::time::sleep(MIN_BACKOFF + Duration::from_millis(500)).await;
+
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+}
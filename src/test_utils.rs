@@ -0,0 +1,272 @@
+//! Mint and ln node for tests
+
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::bail;
+use async_trait::async_trait;
+use bip39::Mnemonic;
+use bitcoin::secp256k1::Secp256k1;
+use cdk::amount::Amount;
+use cdk::cdk_database::{self, MintDatabase};
+use cdk::mint::Mint;
+use cdk::nuts::{
+    BlindSignature, BlindedMessage, CurrencyUnit, Id, MintBolt11Request, MintInfo, Proof,
+    PublicKey, SecretKey, SwapRequest,
+};
+use cdk::types::MintQuote;
+use cdk_redb::MintRedbDatabase;
+use futures::StreamExt;
+use ln_rs::{Bolt11Invoice, InvoiceStatus};
+
+use crate::lightning::{Balance, InvoiceStream, Lightning, Payment};
+use crate::utils::unix_time;
+
+pub type Localstore = Arc<dyn MintDatabase<Err = cdk_database::Error> + Send + Sync>;
+
+pub const MNEMONIC: &str = "inner strategy nasty verify camp bind avoid cause glue holiday dress bounce exotic husband hungry winter diet town spray team number hen upper seek";
+
+/// New redb database in the temp dir
+pub fn test_localstore() -> Localstore {
+    let path =
+        std::env::temp_dir().join(format!("cashu-rs-mint-test-{}.redb", rand::random::<u64>()));
+
+    Arc::new(MintRedbDatabase::new(path.to_str().unwrap()).unwrap())
+}
+
+/// Mint of [`MNEMONIC`] on `localstore`
+pub async fn mint_on(localstore: Localstore) -> Mint {
+    let seed = Mnemonic::from_str(MNEMONIC).unwrap().to_seed_normalized("");
+
+    Mint::new(&seed, MintInfo::default(), localstore, Amount::ZERO, 0.0)
+        .await
+        .unwrap()
+}
+
+/// Mint on a new database
+pub async fn test_mint() -> Mint {
+    mint_on(test_localstore()).await
+}
+
+/// Signed bolt11 invoice for `amount_msat` with a random payment hash
+pub fn test_invoice(amount_msat: u64, description: String) -> Bolt11Invoice {
+    use bitcoin_hashes::{sha256, Hash};
+    use cdk::lightning_invoice::{Currency, InvoiceBuilder, PaymentSecret};
+
+    let node_key = bitcoin::secp256k1::SecretKey::from_slice(&[42; 32]).unwrap();
+
+    let invoice = InvoiceBuilder::new(Currency::Bitcoin)
+        .description(description)
+        .payment_hash(sha256::Hash::hash(&rand::random::<[u8; 32]>()))
+        .payment_secret(PaymentSecret(rand::random()))
+        .amount_milli_satoshis(amount_msat)
+        .current_timestamp()
+        .min_final_cltv_expiry_delta(144)
+        .build_signed(|hash| Secp256k1::new().sign_ecdsa_recoverable(hash, &node_key))
+        .unwrap();
+
+    Bolt11Invoice::from_str(&invoice.to_string()).unwrap()
+}
+
+/// Ln node that reports no invoice paid until told to
+#[derive(Default)]
+pub struct MockLightning {
+    /// Payment hashes of the invoices reported paid
+    paid: std::sync::Mutex<HashSet<String>>,
+    /// Fail to create invoices like a node that is down
+    pub offline: bool,
+    /// Outbound balance reported
+    pub balance_msat: u64,
+    /// Number of invoices paid
+    pub pay_calls: AtomicUsize,
+}
+
+impl MockLightning {
+    /// Report the invoice `request` as paid from now on
+    pub fn settle(&self, request: &str) {
+        let invoice = Bolt11Invoice::from_str(request).unwrap();
+
+        self.paid
+            .lock()
+            .unwrap()
+            .insert(invoice.payment_hash().to_string());
+    }
+}
+
+#[async_trait]
+impl Lightning for MockLightning {
+    async fn create_invoice(
+        &self,
+        amount_msat: u64,
+        description: String,
+    ) -> anyhow::Result<Bolt11Invoice> {
+        if self.offline {
+            bail!("Node is offline");
+        }
+
+        Ok(test_invoice(amount_msat, description))
+    }
+
+    async fn invoice_status(&self, invoice: &Bolt11Invoice) -> anyhow::Result<InvoiceStatus> {
+        let paid = self
+            .paid
+            .lock()
+            .unwrap()
+            .contains(&invoice.payment_hash().to_string());
+
+        Ok(match paid {
+            true => InvoiceStatus::Paid,
+            false => InvoiceStatus::Unpaid,
+        })
+    }
+
+    async fn wait_invoice(&self) -> anyhow::Result<InvoiceStream> {
+        Ok(futures::stream::pending().boxed())
+    }
+
+    async fn pay_invoice(
+        &self,
+        invoice: Bolt11Invoice,
+        _max_fee_msat: u64,
+    ) -> anyhow::Result<Payment> {
+        self.pay_calls.fetch_add(1, Ordering::SeqCst);
+
+        Ok(Payment {
+            preimage: Some(hex::encode([0; 32])),
+            total_spent_msat: invoice.amount_milli_satoshis().unwrap_or_default(),
+        })
+    }
+
+    async fn balance(&self) -> anyhow::Result<Balance> {
+        Ok(Balance {
+            ln_msat: self.balance_msat,
+            on_chain_sat: 0,
+        })
+    }
+}
+
+/// 2500 sat bolt11 invoice from the BOLT 11 test vectors, created at
+/// 1496314658 and expiring 60 seconds later
+pub const INVOICE: &str = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh";
+
+/// Id and published keys of the active sat keyset
+pub async fn sat_keyset(mint: &Mint) -> (Id, serde_json::Value) {
+    let pubkeys = serde_json::to_value(mint.pubkeys().await.unwrap()).unwrap();
+
+    let keyset = pubkeys["keysets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|keyset| keyset["unit"] == "sat")
+        .unwrap();
+
+    (
+        Id::from_str(keyset["id"].as_str().unwrap()).unwrap(),
+        keyset["keys"].clone(),
+    )
+}
+
+/// Blinded message of a random secret and what the wallet keeps to
+/// unblind its signature
+pub struct WalletOutput {
+    pub message: BlindedMessage,
+    pub secret: String,
+    pub r: SecretKey,
+}
+
+pub fn wallet_output(keyset_id: Id, amount: u64) -> WalletOutput {
+    let secret = hex::encode(rand::random::<[u8; 32]>());
+    let (blinded_secret, r) = cdk::dhke::blind_message(secret.as_bytes(), None).unwrap();
+
+    let message = serde_json::from_value(serde_json::json!({
+        "amount": amount,
+        "id": keyset_id,
+        "B_": blinded_secret,
+    }))
+    .unwrap();
+
+    WalletOutput { message, secret, r }
+}
+
+/// Published key of the mint for `amount`
+pub fn amount_key(keys: &serde_json::Value, amount: Amount) -> PublicKey {
+    PublicKey::from_hex(keys[u64::from(amount).to_string()].as_str().unwrap()).unwrap()
+}
+
+/// Unblind `signature` of `output` into a proof
+pub fn unblind(
+    output: &WalletOutput,
+    signature: &BlindSignature,
+    keys: &serde_json::Value,
+) -> Proof {
+    let c =
+        cdk::dhke::unblind_message(&signature.c, &output.r, &amount_key(keys, signature.amount))
+            .unwrap();
+
+    serde_json::from_value(serde_json::json!({
+        "amount": signature.amount,
+        "id": signature.keyset_id,
+        "secret": output.secret,
+        "C": c,
+    }))
+    .unwrap()
+}
+
+/// Mint `amounts` through a paid mint quote
+pub async fn mint_outputs(mint: &Mint, amounts: &[u64]) -> Vec<(WalletOutput, BlindSignature)> {
+    let (keyset_id, _) = sat_keyset(mint).await;
+
+    let outputs: Vec<WalletOutput> = amounts
+        .iter()
+        .map(|amount| wallet_output(keyset_id, *amount))
+        .collect();
+
+    let quote = mint
+        .new_mint_quote(
+            "https://example.com/".to_string().into(),
+            INVOICE.to_string(),
+            CurrencyUnit::Sat,
+            Amount::from(amounts.iter().sum::<u64>()),
+            unix_time() + 60,
+        )
+        .await
+        .unwrap();
+
+    mint.update_mint_quote(MintQuote {
+        paid: true,
+        ..quote.clone()
+    })
+    .await
+    .unwrap();
+
+    let response = mint
+        .process_mint_request(MintBolt11Request {
+            quote: quote.id,
+            outputs: outputs
+                .iter()
+                .map(|output| output.message.clone())
+                .collect(),
+        })
+        .await
+        .unwrap();
+
+    outputs.into_iter().zip(response.signatures).collect()
+}
+
+/// Swap `proofs` for outputs of the same amounts so they are spent
+pub async fn spend(mint: &Mint, keyset_id: Id, proofs: &[Proof]) {
+    let outputs: Vec<BlindedMessage> = proofs
+        .iter()
+        .map(|proof| wallet_output(keyset_id, u64::from(proof.amount)).message)
+        .collect();
+
+    let request: SwapRequest = serde_json::from_value(serde_json::json!({
+        "inputs": proofs,
+        "outputs": outputs,
+    }))
+    .unwrap();
+
+    mint.process_swap_request(request).await.unwrap();
+}
@@ -1,6 +1,105 @@
+use cdk::nuts::{CurrencyUnit, KeySetInfo, ProofState};
+use cdk::Amount;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LnMessage {
     PaymentReceived,
 }
+
+/// NUT-02 keyset with the time range it is valid for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeysetValidity {
+    #[serde(flatten)]
+    pub keyset: KeySetInfo,
+    pub valid_from: u64,
+    pub valid_to: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeysetsResponse {
+    pub keysets: Vec<KeysetValidity>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapValidateResponse {
+    pub valid: bool,
+    pub reason: Option<String>,
+}
+
+/// NUT-04 bolt11 quote request with an optional invoice description
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MintQuoteBolt11Request {
+    pub amount: Amount,
+    pub unit: CurrencyUnit,
+    pub description: Option<String>,
+}
+
+/// Cost of a melt, returned without creating a quote
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeltPreviewResponse {
+    pub amount: Amount,
+    pub fee_reserve: Amount,
+    /// Fee charged on the proofs of the melt
+    pub input_fee: Amount,
+    /// Value of proofs the wallet has to send
+    pub total_required: Amount,
+}
+
+/// NUT-07 request with the `Y`s left unparsed so one bad entry can't fail
+/// the whole batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckStateRequest {
+    #[serde(rename = "Ys")]
+    pub ys: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum InvalidState {
+    Invalid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CheckStateEntry {
+    Valid(ProofState),
+    /// `Y` that is not a valid public key
+    Invalid {
+        #[serde(rename = "Y")]
+        y: String,
+        state: InvalidState,
+        detail: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckStateResponse {
+    pub states: Vec<CheckStateEntry>,
+}
+
+/// Ecash issued and redeemed in one unit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnitAudit {
+    pub unit: CurrencyUnit,
+    pub issued: Amount,
+    /// Quoted amount of paid melts, routing fees paid out of the fee reserve
+    /// are not counted
+    pub redeemed: Amount,
+    /// Issued minus redeemed, over the ecash in circulation by the routing
+    /// fees paid
+    pub outstanding: Amount,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditResponse {
+    pub units: Vec<UnitAudit>,
+    /// Ln balance of the node in sat
+    pub ln_balance: u64,
+    /// Spendable on chain balance of the node in sat
+    pub on_chain_balance: u64,
+    /// Ln balance over outstanding sat and msat ecash, unset when nothing is
+    /// outstanding. As outstanding includes routing fees paid this is a lower
+    /// bound
+    pub backing_ratio: Option<f64>,
+}
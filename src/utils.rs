@@ -1,6 +1,9 @@
 use std::path::PathBuf;
 use std::time::SystemTime;
 
+use bitcoin::bip32::{ChildNumber, DerivationPath};
+use cdk::nuts::CurrencyUnit;
+
 pub fn unix_time() -> u64 {
     SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
@@ -22,3 +25,124 @@ pub fn expand_path(path: &str) -> Option<PathBuf> {
         Some(PathBuf::from(path))
     }
 }
+
+/// Hardened index of the subtree holding every keyset but the first sat one
+///
+/// The keys of a keyset are derived at `<keyset path>/<order>'`. cdk creates
+/// the first sat keyset at `m/0'`, so `m/0'/<n>'` are its keys and no other
+/// keyset may be derived under `m/0'`.
+const KEYSET_SUBTREE_INDEX: u32 = 128;
+
+/// Hardened index of the keysets of a unit within [`KEYSET_SUBTREE_INDEX`]
+fn unit_index(unit: &CurrencyUnit) -> Option<u32> {
+    match unit {
+        CurrencyUnit::Sat => Some(0),
+        CurrencyUnit::Msat => Some(1),
+        _ => None,
+    }
+}
+
+/// Derivation path of the keyset of a unit after `rotation` rotations,
+/// `m/128'/<unit>'/<rotation>'`
+///
+/// Every unit has its own subtree so no two units can derive the same keys.
+pub fn unit_derivation_path(unit: &CurrencyUnit, rotation: u32) -> Option<DerivationPath> {
+    Some(DerivationPath::from(vec![
+        ChildNumber::from_hardened_idx(KEYSET_SUBTREE_INDEX).ok()?,
+        ChildNumber::from_hardened_idx(unit_index(unit)?).ok()?,
+        ChildNumber::from_hardened_idx(rotation).ok()?,
+    ]))
+}
+
+/// Derivation path of the keyset replacing the one at `path`
+///
+/// `path` has to be in the subtree of `unit`. The sat keyset cdk creates for a
+/// new mint is at `m/0'` and counts as rotation 0.
+pub fn next_derivation_path(unit: &CurrencyUnit, path: &DerivationPath) -> Option<DerivationPath> {
+    let subtree = ChildNumber::from_hardened_idx(KEYSET_SUBTREE_INDEX).ok()?;
+    let unit_child = ChildNumber::from_hardened_idx(unit_index(unit)?).ok()?;
+
+    let rotation = match path.as_ref() {
+        [ChildNumber::Hardened { index: 0 }] if *unit == CurrencyUnit::Sat => 0,
+        [root, child, ChildNumber::Hardened { index }]
+            if *root == subtree && *child == unit_child =>
+        {
+            *index
+        }
+        _ => return None,
+    };
+
+    unit_derivation_path(unit, rotation.checked_add(1)?)
+}
+
+/// Convert an amount of `unit` to msat
+///
+/// Returns `None` for units that have no fixed relation to bitcoin.
+pub fn to_msat(amount: u64, unit: &CurrencyUnit) -> Option<u64> {
+    match unit {
+        CurrencyUnit::Sat => amount.checked_mul(1000),
+        CurrencyUnit::Msat => Some(amount),
+        _ => None,
+    }
+}
+
+/// Convert an amount of msat to `unit`, rounding up
+pub fn from_msat_ceil(amount_msat: u64, unit: &CurrencyUnit) -> Option<u64> {
+    match unit {
+        CurrencyUnit::Sat => Some(amount_msat.div_ceil(1000)),
+        CurrencyUnit::Msat => Some(amount_msat),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn rotations_stay_in_unit_subtree() {
+        let initial_sat = DerivationPath::from_str("m/0'").unwrap();
+        let sat = next_derivation_path(&CurrencyUnit::Sat, &initial_sat).unwrap();
+        assert_eq!(sat, DerivationPath::from_str("m/128'/0'/1'").unwrap());
+
+        let sat = next_derivation_path(&CurrencyUnit::Sat, &sat).unwrap();
+        assert_eq!(sat, DerivationPath::from_str("m/128'/0'/2'").unwrap());
+
+        let msat = unit_derivation_path(&CurrencyUnit::Msat, 0).unwrap();
+        assert_eq!(msat, DerivationPath::from_str("m/128'/1'/0'").unwrap());
+        assert_ne!(sat, msat);
+
+        let msat = next_derivation_path(&CurrencyUnit::Msat, &msat).unwrap();
+        assert_eq!(msat, DerivationPath::from_str("m/128'/1'/1'").unwrap());
+    }
+
+    #[test]
+    fn rotations_are_not_keys_of_the_initial_sat_keyset() {
+        let initial_sat = DerivationPath::from_str("m/0'").unwrap();
+
+        for unit in [CurrencyUnit::Sat, CurrencyUnit::Msat] {
+            let mut path = unit_derivation_path(&unit, 0).unwrap();
+
+            for _ in 0..100 {
+                assert!(!path.as_ref().starts_with(initial_sat.as_ref()), "{}", path);
+                path = next_derivation_path(&unit, &path).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn path_outside_unit_subtree_is_refused() {
+        let msat = unit_derivation_path(&CurrencyUnit::Msat, 0).unwrap();
+        assert!(next_derivation_path(&CurrencyUnit::Sat, &msat).is_none());
+
+        // Only sat has a keyset at `m/0'`
+        let initial_sat = DerivationPath::from_str("m/0'").unwrap();
+        assert!(next_derivation_path(&CurrencyUnit::Msat, &initial_sat).is_none());
+
+        // The layout before keysets moved to `m/128'`
+        let old_sat = DerivationPath::from_str("m/0'/1'").unwrap();
+        assert!(next_derivation_path(&CurrencyUnit::Sat, &old_sat).is_none());
+    }
+}
@@ -0,0 +1,171 @@
+//! Notifications of mint activity posted to an operator configured url
+
+use std::time::Duration;
+
+use axum::http::header::CONTENT_TYPE;
+use bitcoin_hashes::{sha256, Hash, HashEngine, Hmac, HmacEngine};
+use cdk::Amount;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::utils::unix_time;
+
+/// Header carrying the hex HMAC-SHA256 of the body keyed with the webhook
+/// secret
+const SIGNATURE_HEADER: &str = "X-Cashu-Signature";
+const TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventType {
+    MintQuotePaid,
+    MeltCompleted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    #[serde(rename = "type")]
+    pub event_type: EventType,
+    pub quote_id: String,
+    pub amount: Amount,
+    pub timestamp: u64,
+}
+
+impl Event {
+    pub fn new(event_type: EventType, quote_id: String, amount: Amount) -> Self {
+        Self {
+            event_type,
+            quote_id,
+            amount,
+            timestamp: unix_time(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    client: reqwest::Client,
+    url: Url,
+    secret: Option<String>,
+}
+
+impl Webhook {
+    pub fn new(url: Url, secret: Option<String>) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder().timeout(TIMEOUT).build()?;
+
+        Ok(Self {
+            client,
+            url,
+            secret,
+        })
+    }
+
+    /// Post `event` in the background, retrying with backoff on failure
+    pub fn send(&self, event: Event) {
+        let webhook = self.clone();
+
+        tokio::spawn(async move {
+            let mut backoff = Duration::from_secs(1);
+
+            for attempt in 1..=MAX_ATTEMPTS {
+                match webhook.post(&event).await {
+                    Ok(()) => {
+                        debug!("Sent webhook for quote {}", event.quote_id);
+                        return;
+                    }
+                    Err(err) => {
+                        warn!(
+                            "Webhook for quote {} failed (attempt {}): {:?}",
+                            event.quote_id, attempt, err
+                        );
+                        if attempt == MAX_ATTEMPTS {
+                            break;
+                        }
+                        tokio::time::sleep(backoff).await;
+                        backoff *= 2;
+                    }
+                }
+            }
+        });
+    }
+
+    async fn post(&self, event: &Event) -> anyhow::Result<()> {
+        let body = serde_json::to_vec(event)?;
+
+        let mut request = self
+            .client
+            .post(self.url.clone())
+            .header(CONTENT_TYPE, "application/json");
+
+        if let Some(secret) = &self.secret {
+            request = request.header(SIGNATURE_HEADER, signature(secret, &body));
+        }
+
+        request.body(body).send().await?.error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// Hex HMAC-SHA256 of `body` keyed with `secret`
+fn signature(secret: &str, body: &[u8]) -> String {
+    let mut engine = HmacEngine::<sha256::Hash>::new(secret.as_bytes());
+    engine.input(body);
+
+    hex::encode(Hmac::<sha256::Hash>::from_engine(engine).to_byte_array())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr};
+    use std::sync::{Arc, Mutex};
+
+    use axum::body::Bytes;
+    use axum::http::HeaderMap;
+    use axum::routing::post;
+    use axum::Router;
+
+    use super::*;
+
+    #[test]
+    fn signature_is_hmac_sha256() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            signature("Jefe", b"what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn posted_body_is_signed() {
+        let received = Arc::new(Mutex::new(None));
+
+        let receiver = received.clone();
+        let router = Router::new().route(
+            "/",
+            post(move |headers: HeaderMap, body: Bytes| async move {
+                let header = headers[SIGNATURE_HEADER].to_str().unwrap().to_string();
+                *receiver.lock().unwrap() = Some((header, body));
+            }),
+        );
+
+        let server = axum::Server::bind(&SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .serve(router.into_make_service());
+        let url = Url::parse(&format!("http://{}/", server.local_addr())).unwrap();
+        tokio::spawn(server);
+
+        let webhook = Webhook::new(url, Some("secret".to_string())).unwrap();
+        let event = Event::new(
+            EventType::MeltCompleted,
+            "quote".to_string(),
+            Amount::from(21),
+        );
+        webhook.post(&event).await.unwrap();
+
+        let (header, body) = received.lock().unwrap().take().unwrap();
+        assert_eq!(header, signature("secret", &body));
+        assert_eq!(body, serde_json::to_vec(&event).unwrap());
+    }
+}